@@ -1,6 +1,11 @@
 BEGIN;
 
 -- Drop tables first (in reverse order of creation to handle dependencies)
+DROP TABLE IF EXISTS test_cases;
+DROP TABLE IF EXISTS libraries;
+DROP TABLE IF EXISTS kv;
+DROP TABLE IF EXISTS environments;
+DROP TABLE IF EXISTS secrets;
 DROP TABLE IF EXISTS pipelines;
 DROP TABLE IF EXISTS nodes;
 DROP TABLE IF EXISTS logs;
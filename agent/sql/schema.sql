@@ -4,32 +4,44 @@ CREATE TYPE log_level AS ENUM ('info', 'warn', 'error');
 
 CREATE TABLE IF NOT EXISTS logs
 (
-    id        SERIAL PRIMARY KEY,
-    level     log_level                NOT NULL,
-    category  TEXT                     NOT NULL,
-    message   TEXT                     NOT NULL,
-    create_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+    id          SERIAL PRIMARY KEY,
+    level       log_level                NOT NULL,
+    category    TEXT                     NOT NULL,
+    message     TEXT                     NOT NULL,
+    create_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    pipeline_id UUID,
+    history_id  UUID
 );
 
 CREATE INDEX IF NOT EXISTS idx_logs_create_at ON logs (create_at);
+CREATE INDEX IF NOT EXISTS idx_logs_pipeline_id ON logs (pipeline_id);
+CREATE INDEX IF NOT EXISTS idx_logs_history_id ON logs (history_id);
+CREATE INDEX IF NOT EXISTS idx_logs_message ON logs USING GIN (to_tsvector('simple', message));
 
 CREATE TABLE IF NOT EXISTS nodes
 (
-    id          UUID PRIMARY KEY DEFAULT gen_random_uuid() NOT NULL,
-    is_internal BOOLEAN          DEFAULT FALSE NOT NULL,
-    name        TEXT   NOT NULL,
-    script      TEXT   NOT NULL,
-    inputs      TEXT[] NOT NULL  DEFAULT '{}',
-    outputs     TEXT[] NOT NULL  DEFAULT '{}'
+    id             UUID PRIMARY KEY DEFAULT gen_random_uuid() NOT NULL,
+    is_internal    BOOLEAN          DEFAULT FALSE NOT NULL,
+    name           TEXT   NOT NULL,
+    script         TEXT   NOT NULL,
+    inputs         TEXT[] NOT NULL  DEFAULT '{}',
+    outputs        TEXT[] NOT NULL  DEFAULT '{}',
+    config_schema  JSON,
+    input_schemas  JSON,
+    output_schemas JSON,
+    language       TEXT   NOT NULL  DEFAULT 'javascript'
 );
 
 CREATE INDEX IF NOT EXISTS idx_nodes_name ON nodes (name);
 
 CREATE TABLE IF NOT EXISTS pipelines
 (
-    id      UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
-    name    TEXT NOT NULL,
-    content JSON NOT NULL
+    id               UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name             TEXT NOT NULL,
+    content          JSON NOT NULL,
+    error_handler_id UUID,
+    allowed_hosts    TEXT[] NOT NULL DEFAULT '{}',
+    network_policy   JSON
 );
 
 CREATE TABLE IF NOT EXISTS history
@@ -40,17 +52,23 @@ CREATE TABLE IF NOT EXISTS history
     start_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
     end_at      TIMESTAMP WITH TIME ZONE,
     error       TEXT,
-    result      JSON
+    result      JSON,
+    trace       JSON,
+    input       JSON,
+    context     JSON,
+    revision    JSON,
+    replay_of   UUID
 );
 
 CREATE INDEX IF NOT EXISTS idx_history_pipeline_id ON history (pipeline_id);
 
 CREATE TABLE IF NOT EXISTS routes
 (
-    id          UUID PRIMARY KEY NOT NULL DEFAULT gen_random_uuid(),
-    pipeline_id UUID             NOT NULL,
-    path        TEXT             NOT NULL,
-    method      TEXT             NOT NULL
+    id             UUID PRIMARY KEY NOT NULL DEFAULT gen_random_uuid(),
+    pipeline_id    UUID             NOT NULL,
+    path           TEXT             NOT NULL,
+    method         TEXT             NOT NULL,
+    environment_id UUID
 );
 
 CREATE TABLE IF NOT EXISTS cron_jobs
@@ -61,4 +79,57 @@ CREATE TABLE IF NOT EXISTS cron_jobs
     input_data  JSON             NOT NULL
 );
 
+CREATE TABLE IF NOT EXISTS secrets
+(
+    id        UUID PRIMARY KEY         NOT NULL DEFAULT gen_random_uuid(),
+    name      TEXT                     NOT NULL UNIQUE,
+    value     BYTEA                    NOT NULL,
+    create_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    update_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS environments
+(
+    id        UUID PRIMARY KEY NOT NULL DEFAULT gen_random_uuid(),
+    name      TEXT             NOT NULL UNIQUE,
+    variables JSON             NOT NULL DEFAULT '{}'
+);
+
+CREATE TABLE IF NOT EXISTS kv
+(
+    namespace TEXT                     NOT NULL,
+    key       TEXT                     NOT NULL,
+    value     JSONB                    NOT NULL,
+    expire_at TIMESTAMP WITH TIME ZONE,
+    update_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    PRIMARY KEY (namespace, key)
+);
+
+CREATE TABLE IF NOT EXISTS libraries
+(
+    id        UUID PRIMARY KEY         NOT NULL DEFAULT gen_random_uuid(),
+    name      TEXT                     NOT NULL UNIQUE,
+    source    TEXT                     NOT NULL,
+    create_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    update_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS test_cases
+(
+    id             UUID PRIMARY KEY         NOT NULL DEFAULT gen_random_uuid(),
+    node_id        UUID,
+    pipeline_id    UUID,
+    name           TEXT                     NOT NULL,
+    input          JSON                     NOT NULL DEFAULT '{}',
+    config         JSON,
+    mocks          JSON,
+    expect         JSON                     NOT NULL DEFAULT '[]',
+    environment_id UUID,
+    create_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    CHECK ((node_id IS NULL) <> (pipeline_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_test_cases_node_id ON test_cases (node_id);
+CREATE INDEX IF NOT EXISTS idx_test_cases_pipeline_id ON test_cases (pipeline_id);
+
 COMMIT;
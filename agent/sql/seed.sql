@@ -15,31 +15,17 @@ VALUES ('EndRequest',
         '{}');
 
 INSERT INTO nodes (name, is_internal, script, inputs, outputs)
-VALUES ('True',
+VALUES ('ForEach',
         TRUE,
         'internal',
-        '{}',
-        ARRAY ['out']);
-
-INSERT INTO nodes (name, is_internal, script, inputs, outputs)
-VALUES ('False',
-        TRUE,
-        'internal',
-        '{}',
-        ARRAY ['out']);
+        ARRAY ['items'],
+        ARRAY ['item', 'index']);
 
 INSERT INTO nodes (name, is_internal, script, inputs, outputs)
-VALUES ('Breaker',
+VALUES ('Collect',
         TRUE,
         'internal',
-        ARRAY ['condition'],
-        '{}');
-
-INSERT INTO nodes (name, is_internal, script, inputs, outputs)
-VALUES ('Empty',
-        TRUE,
-        'internal',
-        '{}',
-        ARRAY ['out']);
+        ARRAY ['value'],
+        ARRAY ['results']);
 
 COMMIT;
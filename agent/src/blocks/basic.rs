@@ -0,0 +1,229 @@
+use crate::blocks::value::bool_value;
+use crate::blocks::{Block, BlockContext};
+use deno_core::error::AnyError;
+use deno_core::v8::Local;
+use serde_json::{json, Value};
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![
+        Box::new(Literal {
+            name: "True",
+            value: true,
+        }),
+        Box::new(Literal {
+            name: "False",
+            value: false,
+        }),
+        Box::new(Empty),
+        Box::new(Constant),
+        Box::new(Breaker),
+        Box::new(Switch),
+        Box::new(Merge),
+    ]
+}
+
+struct Literal {
+    name: &'static str,
+    value: bool,
+}
+
+impl Block for Literal {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "boolean" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = bool_value(ctx.scope, self.value);
+        ctx.output_raw("out", value);
+        Ok(())
+    }
+}
+
+struct Empty;
+
+impl Block for Empty {
+    fn name(&self) -> &'static str {
+        "Empty"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        ctx.output_undefined("out");
+        Ok(())
+    }
+}
+
+// Emits the `value` field of its configuration
+struct Constant;
+
+impl Block for Constant {
+    fn name(&self) -> &'static str {
+        "Constant"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "value": { "type": "any" } },
+            "required": ["value"]
+        }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        match ctx.config.get("value").cloned() {
+            Some(value) => ctx.output("out", &value)?,
+            None => ctx.output_undefined("out"),
+        }
+        Ok(())
+    }
+}
+
+struct Breaker;
+
+impl Block for Breaker {
+    fn name(&self) -> &'static str {
+        "Breaker"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["condition"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "condition": "boolean" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        if !ctx.input::<bool>("condition")? {
+            ctx.halt();
+        }
+        Ok(())
+    }
+}
+
+// Branches are the outputs of the graph node, `branch` is reserved for the selected label
+struct Switch;
+
+impl Block for Switch {
+    fn name(&self) -> &'static str {
+        "Switch"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["branch", "true", "false"]
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let node = ctx.node;
+        let value = ctx
+            .input_raw("value")
+            .cloned()
+            .ok_or_else(|| AnyError::msg("Input value is not connected"))?;
+        let matched = match ctx.input::<Value>("value")? {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+
+        let labels = node
+            .graph_node
+            .data
+            .outputs
+            .iter()
+            .filter(|label| label.as_str() != "branch")
+            .collect::<Vec<_>>();
+        let selected = labels
+            .iter()
+            .find(|label| label.as_str() == matched)
+            .or_else(|| labels.iter().find(|label| label.as_str() == "default"));
+
+        match selected {
+            Some(label) => {
+                ctx.output_raw(label, value);
+                ctx.output("branch", label)?;
+            }
+            None => ctx.output("branch", &Value::Null)?,
+        }
+        Ok(())
+    }
+}
+
+// The first input holding a defined value wins, `branch` names the input it came from
+struct Merge;
+
+impl Block for Merge {
+    fn name(&self) -> &'static str {
+        "Merge"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["a", "b"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out", "branch"]
+    }
+
+    fn lenient(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let node = ctx.node;
+        let mut selected = None;
+        for name in &node.graph_node.data.inputs {
+            if let Some(value) = ctx.input_raw(name).cloned()
+                && !Local::new(ctx.scope, &value).is_undefined()
+            {
+                selected = Some((name, value));
+                break;
+            }
+        }
+
+        match selected {
+            Some((name, value)) => {
+                ctx.output_raw("out", value);
+                ctx.output("branch", name)?;
+            }
+            None => {
+                ctx.output_undefined("out");
+                ctx.output("branch", &Value::Null)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::blocks::{Block, BlockContext};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use deno_core::error::AnyError;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha512};
+use uuid::Uuid;
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![
+        Box::new(Hash),
+        Box::new(HmacSha256),
+        Box::new(Base64Encode),
+        Box::new(Base64Decode),
+        Box::new(RandomUuid),
+    ]
+}
+
+// Hex digest of the string `value`
+struct Hash;
+
+impl Block for Hash {
+    fn name(&self) -> &'static str {
+        "Hash"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "algorithm": { "type": "string", "enum": ["sha256", "sha512"], "default": "sha256" }
+            }
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        let digest = match ctx.config["algorithm"].as_str() {
+            Some("sha512") => hex::encode(Sha512::digest(value.as_bytes())),
+            _ => hex::encode(Sha256::digest(value.as_bytes())),
+        };
+        ctx.output("out", &digest)
+    }
+}
+
+// Hex HMAC-SHA256 signature of `value` with `key`
+struct HmacSha256;
+
+impl Block for HmacSha256 {
+    fn name(&self) -> &'static str {
+        "HmacSha256"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value", "key"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string", "key": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        let key = ctx.input::<String>("key")?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())?;
+        mac.update(value.as_bytes());
+        ctx.output("out", &hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+struct Base64Encode;
+
+impl Block for Base64Encode {
+    fn name(&self) -> &'static str {
+        "Base64Encode"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        ctx.output("out", &STANDARD.encode(value))
+    }
+}
+
+struct Base64Decode;
+
+impl Block for Base64Decode {
+    fn name(&self) -> &'static str {
+        "Base64Decode"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        let decoded = String::from_utf8(STANDARD.decode(value)?)?;
+        ctx.output("out", &decoded)
+    }
+}
+
+struct RandomUuid;
+
+impl Block for RandomUuid {
+    fn name(&self) -> &'static str {
+        "Uuid"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        ctx.output("out", &Uuid::new_v4().to_string())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::blocks::{Block, BlockContext};
+use chrono::{DateTime, TimeDelta, Utc};
+use deno_core::error::AnyError;
+use serde_json::{json, Value};
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![Box::new(Now), Box::new(FormatDate), Box::new(DateAdd)]
+}
+
+struct Now;
+
+impl Block for Now {
+    fn name(&self) -> &'static str {
+        "Now"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        ctx.output("out", &Utc::now().to_rfc3339())
+    }
+}
+
+struct FormatDate;
+
+impl Block for FormatDate {
+    fn name(&self) -> &'static str {
+        "FormatDate"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "format": { "type": "string", "default": "%Y-%m-%d %H:%M:%S" } }
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": ["string", "number"] }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let date = parse_date(&ctx.input::<Value>("value")?)?;
+        let format = ctx.config["format"].as_str().unwrap_or("%Y-%m-%d %H:%M:%S");
+
+        let mut formatted = String::new();
+        std::fmt::write(&mut formatted, format_args!("{}", date.format(format)))
+            .map_err(|_| AnyError::msg(format!("Invalid date format: {}", format)))?;
+        ctx.output("out", &formatted)
+    }
+}
+
+// Shifts the date `value` by `ms` milliseconds
+struct DateAdd;
+
+impl Block for DateAdd {
+    fn name(&self) -> &'static str {
+        "DateAdd"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value", "ms"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": ["string", "number"], "ms": "number" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let date = parse_date(&ctx.input::<Value>("value")?)?;
+        let ms = ctx.input::<f64>("ms")?;
+        ctx.output("out", &add_millis(date, ms)?.to_rfc3339())
+    }
+}
+
+// Amounts beyond the range of dates are rejected rather than saturated
+fn add_millis(date: DateTime<Utc>, ms: f64) -> Result<DateTime<Utc>, AnyError> {
+    if !ms.is_finite() || ms.abs() >= i64::MAX as f64 {
+        return Err(AnyError::msg(format!(
+            "Invalid amount of milliseconds: {}",
+            ms
+        )));
+    }
+    TimeDelta::try_milliseconds(ms as i64)
+        .and_then(|delta| date.checked_add_signed(delta))
+        .ok_or_else(|| AnyError::msg("Date out of range"))
+}
+
+// Dates are RFC 3339 strings or milliseconds since the Unix epoch
+fn parse_date(value: &Value) -> Result<DateTime<Utc>, AnyError> {
+    match value {
+        Value::String(s) => Ok(DateTime::parse_from_rfc3339(s)?.with_timezone(&Utc)),
+        Value::Number(n) => n
+            .as_f64()
+            .and_then(|ms| DateTime::from_timestamp_millis(ms as i64))
+            .ok_or_else(|| AnyError::msg(format!("Invalid timestamp: {}", n))),
+        other => Err(AnyError::msg(format!("Invalid date: {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epoch() -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(0).unwrap()
+    }
+
+    #[test]
+    fn adds_milliseconds() {
+        let shifted = add_millis(epoch(), 1500.0).unwrap();
+        assert_eq!(shifted.timestamp_millis(), 1500);
+        let shifted = add_millis(epoch(), -1500.0).unwrap();
+        assert_eq!(shifted.timestamp_millis(), -1500);
+    }
+
+    #[test]
+    fn rejects_amounts_out_of_range() {
+        for ms in [
+            1e300,
+            -1e300,
+            i64::MAX as f64,
+            9e15,
+            f64::NAN,
+            f64::INFINITY,
+        ] {
+            assert!(add_millis(epoch(), ms).is_err(), "{} was accepted", ms);
+        }
+    }
+}
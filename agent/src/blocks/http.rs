@@ -0,0 +1,232 @@
+use crate::blocks::{AsyncBlockContext, AsyncBlockOutput, Block, BlockFuture};
+use crate::sandbox::host_allowed;
+use deno_core::error::AnyError;
+use reqwest::Method;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use url::Url;
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![Box::new(Http)]
+}
+
+// Sends a request described by its configuration, `{{name}}` in the URL is filled from `params`
+struct Http;
+
+impl Block for Http {
+    fn name(&self) -> &'static str {
+        "Http"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["params", "headers", "body"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["status", "headers", "body"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "method": {
+                    "type": "string",
+                    "enum": ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD"],
+                    "default": "GET"
+                },
+                "url": { "type": "string" },
+                "headers": { "type": "object", "default": {} },
+                "timeoutMs": { "type": "integer", "default": 10000 },
+                "responseType": {
+                    "type": "string",
+                    "enum": ["auto", "json", "text"],
+                    "default": "auto"
+                },
+                "failOnError": { "type": "boolean", "default": true }
+            },
+            "required": ["url"]
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "params": "object", "headers": "object" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "status": "integer", "headers": "object" }))
+    }
+
+    fn is_async(&self) -> bool {
+        true
+    }
+
+    fn execute_async(&self, ctx: AsyncBlockContext) -> BlockFuture {
+        Box::pin(async move {
+            let mut details = json!({});
+            let outputs = send(&ctx, &mut details).await;
+            AsyncBlockOutput {
+                outputs,
+                details: Some(details),
+            }
+        })
+    }
+}
+
+async fn send(
+    ctx: &AsyncBlockContext,
+    details: &mut Value,
+) -> Result<HashMap<String, Value>, AnyError> {
+    let config = &ctx.config;
+    let method = config["method"].as_str().unwrap_or("GET");
+    let template = config["url"].as_str().unwrap_or_default();
+    let timeout_ms = config["timeoutMs"].as_u64().unwrap_or(10000);
+    let response_type = config["responseType"].as_str().unwrap_or("auto");
+    let fail_on_error = config["failOnError"].as_bool().unwrap_or(true);
+
+    let url = render_url(template, ctx.inputs.get("params"));
+    let mut headers = Map::new();
+    for source in [config.get("headers"), ctx.inputs.get("headers")] {
+        if let Some(Value::Object(source)) = source {
+            headers.extend(source.clone());
+        }
+    }
+    let body = ctx.inputs.get("body").filter(|body| !body.is_null());
+    details["request"] = json!({
+        "method": method,
+        "url": url,
+        "headers": headers,
+        "body": body,
+    });
+
+    let url = Url::parse(&url)?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| AnyError::msg(format!("URL has no host: {}", url)))?;
+    if !host_allowed(host, &ctx.allowed_hosts) {
+        return Err(AnyError::msg(format!(
+            "Host {} is not in the allowlist of this pipeline",
+            host
+        )));
+    }
+    ctx.network
+        .check_url(&url)
+        .map_err(|reason| AnyError::msg(format!("Network access denied: {}", reason)))?;
+
+    // Redirects are not followed, their targets would bypass the checks above. The resolver
+    // checks the addresses of the host, which are connected to directly rather than through a
+    // system proxy resolving the host again.
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(timeout_ms))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(ctx.network.resolver())
+        .no_proxy()
+        .build()?;
+    let mut request = client.request(Method::from_bytes(method.as_bytes())?, url.clone());
+    for (name, value) in &headers {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        request = request.header(name, value);
+    }
+    request = match body {
+        Some(Value::String(s)) => request.body(s.clone()),
+        Some(other) => request.json(other),
+        None => request,
+    };
+
+    let response = request.send().await.map_err(|e| match e.is_timeout() {
+        true => AnyError::msg(format!(
+            "Request to {} timed out after {} ms",
+            url, timeout_ms
+        )),
+        false => AnyError::msg(format!("Request to {} failed: {}", url, e)),
+    })?;
+
+    let status = response.status().as_u16();
+    let response_headers: Map<String, Value> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or_default().to_string();
+            (name.to_string(), Value::String(value))
+        })
+        .collect();
+    let is_json = response_headers
+        .get("content-type")
+        .and_then(|value| value.as_str())
+        .is_some_and(|value| value.contains("json"));
+    let text = read_body(response, ctx.network.max_response_bytes()).await?;
+    let response_body = match response_type {
+        "json" => serde_json::from_str(&text)?,
+        "text" => Value::String(text),
+        _ if is_json => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        _ => Value::String(text),
+    };
+    details["response"] = json!({
+        "status": status,
+        "headers": response_headers,
+        "body": response_body,
+    });
+
+    if fail_on_error && status >= 400 {
+        return Err(AnyError::msg(format!(
+            "Request to {} returned status {}",
+            url, status
+        )));
+    }
+
+    Ok(HashMap::from([
+        ("status".to_string(), json!(status)),
+        ("headers".to_string(), Value::Object(response_headers)),
+        ("body".to_string(), response_body),
+    ]))
+}
+
+// Reads the response body as text, failing as soon as it grows past the size limit
+async fn read_body(
+    mut response: reqwest::Response,
+    max_response_bytes: Option<u64>,
+) -> Result<String, AnyError> {
+    let max_response_bytes = max_response_bytes.unwrap_or(u64::MAX);
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() as u64 > max_response_bytes {
+            return Err(AnyError::msg(format!(
+                "Response exceeds the limit of {} bytes",
+                max_response_bytes
+            )));
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// Replaces every `{{name}}` with the percent-encoded parameter of the same name
+fn render_url(template: &str, params: Option<&Value>) -> String {
+    let mut url = template.to_string();
+    if let Some(Value::Object(params)) = params {
+        for (name, value) in params {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            url = url.replace(&format!("{{{{{}}}}}", name), &encode(&value));
+        }
+    }
+    url
+}
+
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
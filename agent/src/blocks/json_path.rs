@@ -0,0 +1,88 @@
+use crate::blocks::{Block, BlockContext};
+use deno_core::error::AnyError;
+use serde_json::{json, Value};
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![Box::new(JsonPath)]
+}
+
+// Reads the value at `path` from `value`, `out` is undefined when nothing is there
+struct JsonPath;
+
+impl Block for JsonPath {
+    fn name(&self) -> &'static str {
+        "JsonPath"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "path": { "type": "string", "default": "$" } }
+        }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<Value>("value")?;
+        let path = ctx.config["path"].as_str().unwrap_or("$");
+        let segments = parse(path)?;
+
+        let found = segments
+            .iter()
+            .try_fold(&value, |current, segment| match segment {
+                Segment::Key(key) => current.get(key),
+                Segment::Index(index) => current.get(index),
+            });
+        match found.cloned() {
+            Some(found) => ctx.output("out", &found)?,
+            None => ctx.output_undefined("out"),
+        }
+        Ok(())
+    }
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+// Supports `$.key`, `$[0]` and `$['key']` segments
+fn parse(path: &str) -> Result<Vec<Segment>, AnyError> {
+    let invalid = || AnyError::msg(format!("Invalid JSON path: {}", path));
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = vec![];
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(Segment::Key(tail[..end].to_string()));
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or_else(invalid)?;
+            let inner = &tail[..end];
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+            match quoted {
+                Some(key) => segments.push(Segment::Key(key.to_string())),
+                None => segments.push(Segment::Index(inner.parse().map_err(|_| invalid())?)),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(segments)
+}
@@ -0,0 +1,133 @@
+use crate::blocks::{Block, BlockContext};
+use deno_core::error::AnyError;
+use serde_json::{json, Value};
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![
+        Box::new(Arithmetic {
+            name: "Add",
+            op: |a, b| Ok(a + b),
+        }),
+        Box::new(Arithmetic {
+            name: "Subtract",
+            op: |a, b| Ok(a - b),
+        }),
+        Box::new(Arithmetic {
+            name: "Multiply",
+            op: |a, b| Ok(a * b),
+        }),
+        Box::new(Arithmetic {
+            name: "Divide",
+            op: |a, b| {
+                if b == 0.0 {
+                    return Err(AnyError::msg("Division by zero"));
+                }
+                Ok(a / b)
+            },
+        }),
+        Box::new(Arithmetic {
+            name: "Modulo",
+            op: |a, b| {
+                if b == 0.0 {
+                    return Err(AnyError::msg("Division by zero"));
+                }
+                Ok(a % b)
+            },
+        }),
+        Box::new(Arithmetic {
+            name: "Power",
+            op: |a, b| Ok(a.powf(b)),
+        }),
+        Box::new(Arithmetic {
+            name: "Min",
+            op: |a, b| Ok(a.min(b)),
+        }),
+        Box::new(Arithmetic {
+            name: "Max",
+            op: |a, b| Ok(a.max(b)),
+        }),
+        Box::new(Rounding {
+            name: "Round",
+            op: f64::round,
+        }),
+        Box::new(Rounding {
+            name: "Floor",
+            op: f64::floor,
+        }),
+        Box::new(Rounding {
+            name: "Ceil",
+            op: f64::ceil,
+        }),
+        Box::new(Rounding {
+            name: "Abs",
+            op: f64::abs,
+        }),
+    ]
+}
+
+// Combines the numbers `a` and `b` into `out`
+struct Arithmetic {
+    name: &'static str,
+    op: fn(f64, f64) -> Result<f64, AnyError>,
+}
+
+impl Block for Arithmetic {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["a", "b"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "a": "number", "b": "number" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "number" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let a = ctx.input::<f64>("a")?;
+        let b = ctx.input::<f64>("b")?;
+        ctx.output("out", &(self.op)(a, b)?)
+    }
+}
+
+// Maps the number `value` to `out`
+struct Rounding {
+    name: &'static str,
+    op: fn(f64) -> f64,
+}
+
+impl Block for Rounding {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "number" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "number" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<f64>("value")?;
+        ctx.output("out", &(self.op)(value))
+    }
+}
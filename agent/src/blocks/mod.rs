@@ -1 +1,199 @@
-pub mod boolean;
+pub mod basic;
+pub mod crypto;
+pub mod date;
+pub mod http;
+pub mod json_path;
+pub mod math;
+pub mod string;
+pub mod value;
+
+use crate::db::upsert_internal_node;
+use crate::executor::CombinedNode;
+use crate::sandbox::NetworkSandbox;
+use deno_core::error::AnyError;
+use deno_core::v8::{Global, HandleScope, Local};
+use deno_core::{serde_v8, v8};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use tokio_postgres::Client;
+use value::{native_value, undefined};
+
+/// A built-in node implemented in Rust instead of a script.
+pub trait Block: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn inputs(&self) -> Vec<&'static str>;
+
+    fn outputs(&self) -> Vec<&'static str>;
+
+    fn config_schema(&self) -> Option<Value> {
+        None
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        None
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        None
+    }
+
+    /// Whether the block runs with only some of its inputs available.
+    fn lenient(&self) -> bool {
+        false
+    }
+
+    /// Whether the block runs through `execute_async` instead of `execute`.
+    fn is_async(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, _ctx: &mut BlockContext) -> Result<(), AnyError> {
+        Err(AnyError::msg(format!(
+            "Block {} can only be executed asynchronously",
+            self.name()
+        )))
+    }
+
+    fn execute_async(&self, _ctx: AsyncBlockContext) -> BlockFuture {
+        let name = self.name();
+        Box::pin(async move {
+            AsyncBlockOutput {
+                outputs: Err(AnyError::msg(format!(
+                    "Block {} can only be executed synchronously",
+                    name
+                ))),
+                details: None,
+            }
+        })
+    }
+}
+
+pub type BlockFuture = Pin<Box<dyn Future<Output = AsyncBlockOutput>>>;
+
+/// Inputs and configuration of an asynchronous block, converted to JSON before it is awaited.
+pub struct AsyncBlockContext {
+    pub config: Value,
+    pub inputs: HashMap<String, Value>,
+    pub allowed_hosts: Vec<String>,
+    pub network: NetworkSandbox,
+}
+
+/// Result of an asynchronous block, `details` is recorded in the execution trace even on failure.
+pub struct AsyncBlockOutput {
+    pub outputs: Result<HashMap<String, Value>, AnyError>,
+    pub details: Option<Value>,
+}
+
+/// Inputs, configuration and outputs of a single block execution.
+pub struct BlockContext<'a, 's> {
+    pub scope: &'a mut HandleScope<'s>,
+    pub node: &'a CombinedNode,
+    pub config: Value,
+    inputs: HashMap<String, Global<v8::Value>>,
+    outputs: HashMap<String, Global<v8::Value>>,
+    halted: bool,
+}
+
+impl<'a, 's> BlockContext<'a, 's> {
+    pub fn new(
+        scope: &'a mut HandleScope<'s>,
+        node: &'a CombinedNode,
+        config: Value,
+        inputs: HashMap<String, Global<v8::Value>>,
+    ) -> Self {
+        BlockContext {
+            scope,
+            node,
+            config,
+            inputs,
+            outputs: HashMap::new(),
+            halted: false,
+        }
+    }
+
+    pub fn input<T: DeserializeOwned>(&mut self, name: &str) -> Result<T, AnyError> {
+        let value = self
+            .inputs
+            .get(name)
+            .ok_or_else(|| AnyError::msg(format!("Input {} is not connected", name)))?;
+        let value = Local::new(self.scope, value);
+        serde_v8::from_v8(self.scope, value).map_err(|e| {
+            AnyError::msg(format!(
+                "Input {} of {} is invalid: {}",
+                name, self.node.db_node.name, e
+            ))
+        })
+    }
+
+    pub fn input_raw(&self, name: &str) -> Option<&Global<v8::Value>> {
+        self.inputs.get(name)
+    }
+
+    pub fn output<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), AnyError> {
+        let value = serde_json::to_value(value)?;
+        let value = native_value(self.scope, &value)?;
+        self.outputs.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn output_raw(&mut self, name: &str, value: Global<v8::Value>) {
+        self.outputs.insert(name.to_string(), value);
+    }
+
+    pub fn output_undefined(&mut self, name: &str) {
+        let value = undefined(self.scope);
+        self.outputs.insert(name.to_string(), value);
+    }
+
+    /// Stops the branches following this node.
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub fn finish(self) -> (HashMap<String, Global<v8::Value>>, bool) {
+        (self.outputs, self.halted)
+    }
+}
+
+static REGISTRY: LazyLock<HashMap<&'static str, Box<dyn Block>>> = LazyLock::new(|| {
+    basic::blocks()
+        .into_iter()
+        .chain(math::blocks())
+        .chain(string::blocks())
+        .chain(json_path::blocks())
+        .chain(date::blocks())
+        .chain(http::blocks())
+        .chain(crypto::blocks())
+        .map(|block| (block.name(), block))
+        .collect()
+});
+
+pub fn get_block(name: &str) -> Option<&'static dyn Block> {
+    REGISTRY.get(name).map(|block| block.as_ref())
+}
+
+/// Writes every registered block into the `nodes` table so they can be placed in pipelines.
+pub async fn seed_blocks(client: &Client) -> Result<(), AnyError> {
+    for block in REGISTRY.values() {
+        let inputs: Vec<String> = block.inputs().into_iter().map(String::from).collect();
+        let outputs: Vec<String> = block.outputs().into_iter().map(String::from).collect();
+        upsert_internal_node(
+            client,
+            block.name(),
+            &inputs,
+            &outputs,
+            block.config_schema().as_ref(),
+            block.input_schemas().as_ref(),
+            block.output_schemas().as_ref(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::blocks::{Block, BlockContext};
+use deno_core::error::AnyError;
+use serde_json::{json, Value};
+
+pub fn blocks() -> Vec<Box<dyn Block>> {
+    vec![
+        Box::new(Transform {
+            name: "Uppercase",
+            op: |s| json!(s.to_uppercase()),
+        }),
+        Box::new(Transform {
+            name: "Lowercase",
+            op: |s| json!(s.to_lowercase()),
+        }),
+        Box::new(Transform {
+            name: "Trim",
+            op: |s| json!(s.trim()),
+        }),
+        Box::new(Transform {
+            name: "Length",
+            op: |s| json!(s.chars().count()),
+        }),
+        Box::new(Concat),
+        Box::new(Split),
+        Box::new(Join),
+        Box::new(Replace),
+    ]
+}
+
+// Maps the string `value` to `out`
+struct Transform {
+    name: &'static str,
+    op: fn(&str) -> Value,
+}
+
+impl Block for Transform {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        ctx.output("out", &(self.op)(&value))
+    }
+}
+
+struct Concat;
+
+impl Block for Concat {
+    fn name(&self) -> &'static str {
+        "Concat"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["a", "b"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "a": "string", "b": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let a = ctx.input::<String>("a")?;
+        let b = ctx.input::<String>("b")?;
+        ctx.output("out", &(a + b.as_str()))
+    }
+}
+
+struct Split;
+
+impl Block for Split {
+    fn name(&self) -> &'static str {
+        "Split"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "separator": { "type": "string", "default": "," } }
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": { "type": "array", "items": { "type": "string" } } }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        let separator = ctx.config["separator"].as_str().unwrap_or(",").to_string();
+        let parts: Vec<&str> = value.split(separator.as_str()).collect();
+        ctx.output("out", &parts)
+    }
+}
+
+struct Join;
+
+impl Block for Join {
+    fn name(&self) -> &'static str {
+        "Join"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["values"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": { "separator": { "type": "string", "default": "," } }
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "values": "array" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let values = ctx.input::<Vec<Value>>("values")?;
+        let separator = ctx.config["separator"].as_str().unwrap_or(",");
+        let joined = values
+            .iter()
+            .map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(separator);
+        ctx.output("out", &joined)
+    }
+}
+
+struct Replace;
+
+impl Block for Replace {
+    fn name(&self) -> &'static str {
+        "Replace"
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn outputs(&self) -> Vec<&'static str> {
+        vec!["out"]
+    }
+
+    fn config_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "pattern": { "type": "string" },
+                "replacement": { "type": "string", "default": "" }
+            },
+            "required": ["pattern"]
+        }))
+    }
+
+    fn input_schemas(&self) -> Option<Value> {
+        Some(json!({ "value": "string" }))
+    }
+
+    fn output_schemas(&self) -> Option<Value> {
+        Some(json!({ "out": "string" }))
+    }
+
+    fn execute(&self, ctx: &mut BlockContext) -> Result<(), AnyError> {
+        let value = ctx.input::<String>("value")?;
+        let pattern = ctx.config["pattern"].as_str().unwrap_or_default();
+        let replacement = ctx.config["replacement"].as_str().unwrap_or_default();
+        let replaced = match pattern {
+            "" => value,
+            pattern => value.replace(pattern, replacement),
+        };
+        ctx.output("out", &replaced)
+    }
+}
@@ -0,0 +1,24 @@
+use deno_core::_ops::RustToV8NoScope;
+use deno_core::error::AnyError;
+use deno_core::serde_v8::to_v8;
+use deno_core::v8;
+use deno_core::v8::{Global, HandleScope};
+
+/// Converts a JSON value into a V8 value that can be stored as a node output.
+pub fn native_value(
+    scope: &mut HandleScope,
+    value: &serde_json::Value,
+) -> Result<Global<v8::Value>, AnyError> {
+    let obj = to_v8(scope, value)?;
+    Ok(Global::new(scope, obj))
+}
+
+pub fn bool_value(scope: &mut HandleScope, value: bool) -> Global<v8::Value> {
+    let obj = v8::Boolean::new(scope, value).to_v8();
+    Global::new(scope, obj)
+}
+
+pub fn undefined(scope: &mut HandleScope) -> Global<v8::Value> {
+    let obj = v8::undefined(scope).to_v8();
+    Global::new(scope, obj)
+}
@@ -0,0 +1,58 @@
+use crate::db::models::Environment;
+use crate::db::utils::row_to_environment;
+use anyhow::Result;
+use serde_json::Value;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+pub async fn create_environment(client: &Client, name: &str, variables: &Value) -> Result<Environment> {
+    let row = client
+        .query_one(
+            "INSERT INTO environments (name, variables) VALUES ($1, $2) RETURNING id, name, variables",
+            &[&name, &variables],
+        )
+        .await?;
+    Ok(row_to_environment(row))
+}
+
+pub async fn get_environment(client: &Client, id: Uuid) -> Result<Option<Environment>> {
+    let row = client
+        .query_opt(
+            "SELECT id, name, variables FROM environments WHERE id = $1",
+            &[&id],
+        )
+        .await?;
+    Ok(row.map(row_to_environment))
+}
+
+pub async fn list_environments(client: &Client, limit: i64, offset: i64) -> Result<Vec<Environment>> {
+    let rows = client
+        .query(
+            "SELECT id, name, variables FROM environments ORDER BY name ASC LIMIT $1 OFFSET $2",
+            &[&limit, &offset],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_environment).collect())
+}
+
+pub async fn update_environment(
+    client: &Client,
+    id: Uuid,
+    name: &str,
+    variables: &Value,
+) -> Result<Option<Environment>> {
+    let row = client
+        .query_opt(
+            "UPDATE environments SET name = $2, variables = $3 WHERE id = $1 RETURNING id, name, variables",
+            &[&id, &name, &variables],
+        )
+        .await?;
+    Ok(row.map(row_to_environment))
+}
+
+pub async fn delete_environment(client: &Client, id: Uuid) -> Result<bool> {
+    let rows_affected = client
+        .execute("DELETE FROM environments WHERE id = $1", &[&id])
+        .await?;
+    Ok(rows_affected > 0)
+}
@@ -30,6 +30,123 @@ pub struct GraphNodeData {
     pub name: String,
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
+    /// Static configuration of this node instance, passed to `handle` as its second argument.
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub join: JoinMode,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Narrows the network access of this node beyond the global and pipeline policies.
+    #[serde(default)]
+    pub network: Option<NetworkPolicy>,
+}
+
+/// How a node reached by several control edges decides when to run. Graphs saved before join
+/// modes existed set none and keep running such nodes as soon as a branch reaches them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum JoinMode {
+    /// Run once every incoming branch has either fired or been pruned.
+    WaitAll,
+    /// Run as soon as the first incoming branch fires, later arrivals are ignored.
+    #[default]
+    FirstArrival,
+    /// Like `WaitAll`, but inputs whose source never ran are left undefined.
+    AnyAvailable,
+}
+
+/// Retries a failing node before its error is handled or aborts the run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub backoff_ms: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+    /// Upper bound of the random delay added to every backoff.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Only errors whose message contains one of these patterns are retried, all errors are
+    /// retried when empty.
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+/// Longest delay between two attempts, whatever the policy asks for.
+pub const MAX_BACKOFF_MS: u64 = 60 * 60 * 1000;
+const MAX_ATTEMPTS: u32 = 100;
+const MAX_BACKOFF_MULTIPLIER: f64 = 10.0;
+
+impl RetryPolicy {
+    /// Delay before the given attempt, without jitter. `attempt` counts the attempts made so
+    /// far, so the first retry waits `backoff_ms`.
+    pub fn backoff(&self, attempt: u32) -> u64 {
+        let max_backoff_ms = self
+            .max_backoff_ms
+            .map_or(MAX_BACKOFF_MS, |max| max.min(MAX_BACKOFF_MS));
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.backoff_ms as f64 * self.backoff_multiplier.powi(exponent);
+        // An overflow to infinity or NaN waits as long as the policy allows
+        if delay.is_nan() {
+            return max_backoff_ms;
+        }
+        delay.clamp(0.0, max_backoff_ms as f64) as u64
+    }
+
+    /// Problems with the policy found when its graph is saved.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        if !(1..=MAX_ATTEMPTS).contains(&self.max_attempts) {
+            errors.push(format!(
+                "retry.maxAttempts must be between 1 and {}",
+                MAX_ATTEMPTS
+            ));
+        }
+        if !(1.0..=MAX_BACKOFF_MULTIPLIER).contains(&self.backoff_multiplier) {
+            errors.push(format!(
+                "retry.backoffMultiplier must be between 1 and {}",
+                MAX_BACKOFF_MULTIPLIER
+            ));
+        }
+        for (field, value) in [
+            ("backoffMs", Some(self.backoff_ms)),
+            ("maxBackoffMs", self.max_backoff_ms),
+            ("jitterMs", Some(self.jitter_ms)),
+        ] {
+            if value.is_some_and(|value| value > MAX_BACKOFF_MS) {
+                errors.push(format!(
+                    "retry.{} must be at most {}",
+                    field, MAX_BACKOFF_MS
+                ));
+            }
+        }
+        errors
+    }
+}
+
+/// Outbound network restrictions, fields left empty do not restrict anything.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicy {
+    /// Hosts that may be reached, `*.example.com` also matches subdomains.
+    #[serde(default)]
+    pub allowed_hosts: Option<Vec<String>>,
+    #[serde(default)]
+    pub allowed_ports: Option<Vec<u16>>,
+    /// Rejects loopback, private, link-local and other non-public addresses.
+    #[serde(default)]
+    pub deny_private: Option<bool>,
+    #[serde(default)]
+    pub max_response_bytes: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,3 +165,64 @@ pub struct GraphEdge {
     pub target_handle: String,
     pub id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(
+        backoff_ms: u64,
+        backoff_multiplier: f64,
+        max_backoff_ms: Option<u64>,
+    ) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff_ms,
+            backoff_multiplier,
+            max_backoff_ms,
+            jitter_ms: 0,
+            retry_on: vec![],
+        }
+    }
+
+    #[test]
+    fn backoff_grows_with_every_attempt() {
+        let policy = policy(100, 2.0, None);
+
+        assert_eq!(policy.backoff(1), 100);
+        assert_eq!(policy.backoff(2), 200);
+        assert_eq!(policy.backoff(3), 400);
+    }
+
+    #[test]
+    fn backoff_is_capped_by_the_policy() {
+        assert_eq!(policy(100, 2.0, Some(250)).backoff(3), 250);
+    }
+
+    #[test]
+    fn backoff_is_capped_without_a_maximum() {
+        assert_eq!(policy(100, 10.0, None).backoff(u32::MAX), MAX_BACKOFF_MS);
+        assert_eq!(
+            policy(u64::MAX, f64::MAX, Some(u64::MAX)).backoff(2),
+            MAX_BACKOFF_MS
+        );
+        assert_eq!(policy(100, f64::NAN, None).backoff(2), MAX_BACKOFF_MS);
+        assert_eq!(policy(100, -2.0, None).backoff(2), 0);
+    }
+
+    #[test]
+    fn sensible_policies_are_valid() {
+        assert!(policy(100, 2.0, Some(1000)).validate().is_empty());
+    }
+
+    #[test]
+    fn absurd_policies_are_rejected() {
+        let mut absurd = policy(u64::MAX, -1.0, Some(u64::MAX));
+        absurd.max_attempts = 0;
+        absurd.jitter_ms = u64::MAX;
+
+        assert_eq!(absurd.validate().len(), 5);
+        assert_eq!(policy(100, f64::NAN, None).validate().len(), 1);
+        assert_eq!(policy(100, 1000.0, None).validate().len(), 1);
+    }
+}
@@ -6,11 +6,20 @@ use serde_json::Value;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
-pub async fn create_history(client: &Client, pipeline_id: Uuid, status: &str) -> Result<History> {
+/// Creates the history of a run, along with the request it handles so it can be replayed.
+pub async fn create_history(
+    client: &Client,
+    pipeline_id: Uuid,
+    status: &str,
+    input: &Value,
+    context: &Value,
+    revision: &Value,
+    replay_of: Option<Uuid>,
+) -> Result<History> {
     let row = client
         .query_one(
-            "INSERT INTO history (pipeline_id, status) VALUES ($1, $2) RETURNING id, pipeline_id, status, start_at, end_at, error, result",
-            &[&pipeline_id, &status],
+            "INSERT INTO history (pipeline_id, status, input, context, revision, replay_of) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of",
+            &[&pipeline_id, &status, &input, &context, &revision, &replay_of],
         )
         .await?;
     Ok(row_to_history(row))
@@ -19,7 +28,7 @@ pub async fn create_history(client: &Client, pipeline_id: Uuid, status: &str) ->
 pub async fn get_history(client: &Client, id: Uuid) -> Result<Option<History>> {
     let row = client
         .query_opt(
-            "SELECT id, pipeline_id, status, start_at, end_at, error, result FROM history WHERE id = $1",
+            "SELECT id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of FROM history WHERE id = $1",
             &[&id],
         )
         .await?;
@@ -29,7 +38,7 @@ pub async fn get_history(client: &Client, id: Uuid) -> Result<Option<History>> {
 pub async fn list_histories(client: &Client, limit: i64, offset: i64) -> Result<Vec<History>> {
     let rows = client
         .query(
-            "SELECT id, pipeline_id, status, start_at, end_at, error, result FROM history ORDER BY start_at DESC LIMIT $1 OFFSET $2",
+            "SELECT id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of FROM history ORDER BY start_at DESC LIMIT $1 OFFSET $2",
             &[&limit, &offset],
         )
         .await?;
@@ -44,7 +53,7 @@ pub async fn list_histories_by_pipeline(
 ) -> Result<Vec<History>> {
     let rows = client
         .query(
-            "SELECT id, pipeline_id, status, start_at, end_at, error, result FROM history WHERE pipeline_id = $1 ORDER BY start_at DESC LIMIT $2 OFFSET $3",
+            "SELECT id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of FROM history WHERE pipeline_id = $1 ORDER BY start_at DESC LIMIT $2 OFFSET $3",
             &[&pipeline_id, &limit, &offset],
         )
         .await?;
@@ -58,7 +67,7 @@ pub async fn update_history_status(
 ) -> Result<Option<History>> {
     let row = client
         .query_opt(
-            "UPDATE history SET status = $2 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result",
+            "UPDATE history SET status = $2 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of",
             &[&id, &status],
         )
         .await?;
@@ -74,7 +83,7 @@ pub async fn complete_history(
     let now = Utc::now();
     let row = client
         .query_opt(
-            "UPDATE history SET status = $2, end_at = $3, result = $4 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result",
+            "UPDATE history SET status = $2, end_at = $3, result = $4 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of",
             &[&id, &status, &now, &result],
         )
         .await?;
@@ -93,13 +102,20 @@ pub async fn fail_history(client: &Client, id: Uuid, error: &str) -> Result<Opti
     let now = Utc::now();
     let row = client
         .query_opt(
-            "UPDATE history SET status = 'failed', end_at = $2, error = $3 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result",
+            "UPDATE history SET status = 'failed', end_at = $2, error = $3 WHERE id = $1 RETURNING id, pipeline_id, status, start_at, end_at, error, result, trace, input, context, revision, replay_of",
             &[&id, &now, &error],
         )
         .await?;
     Ok(row.map(row_to_history))
 }
 
+pub async fn update_history_trace(client: &Client, id: Uuid, trace: &Value) -> Result<()> {
+    client
+        .execute("UPDATE history SET trace = $2 WHERE id = $1", &[&id, &trace])
+        .await?;
+    Ok(())
+}
+
 pub async fn delete_history(client: &Client, id: Uuid) -> Result<bool> {
     let rows_affected = client
         .execute("DELETE FROM history WHERE id = $1", &[&id])
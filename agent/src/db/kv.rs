@@ -0,0 +1,134 @@
+use crate::db::models::{KvEntry, KvNamespace};
+use crate::db::utils::{row_to_kv_entry, row_to_kv_namespace};
+use anyhow::Result;
+use serde_json::Value;
+use tokio_postgres::Client;
+
+// Entries past their expiry are treated as absent until they are overwritten or cleared
+const LIVE: &str = "(expire_at IS NULL OR expire_at > now())";
+
+pub async fn kv_get(client: &Client, namespace: &str, key: &str) -> Result<Option<Value>> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT value FROM kv WHERE namespace = $1 AND key = $2 AND {}",
+                LIVE
+            ),
+            &[&namespace, &key],
+        )
+        .await?;
+    Ok(row.map(|row| row.get("value")))
+}
+
+/// Stores a value, expiring after `ttl_ms` milliseconds when given.
+pub async fn kv_set(
+    client: &Client,
+    namespace: &str,
+    key: &str,
+    value: &Value,
+    ttl_ms: Option<i64>,
+) -> Result<()> {
+    client
+        .execute(
+            "INSERT INTO kv (namespace, key, value, expire_at) \
+             VALUES ($1, $2, $3, now() + $4::BIGINT * INTERVAL '1 millisecond') \
+             ON CONFLICT (namespace, key) DO UPDATE \
+             SET value = EXCLUDED.value, expire_at = EXCLUDED.expire_at, update_at = now()",
+            &[&namespace, &key, &value, &ttl_ms],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn kv_delete(client: &Client, namespace: &str, key: &str) -> Result<bool> {
+    let rows_affected = client
+        .execute(
+            &format!(
+                "DELETE FROM kv WHERE namespace = $1 AND key = $2 AND {}",
+                LIVE
+            ),
+            &[&namespace, &key],
+        )
+        .await?;
+    Ok(rows_affected > 0)
+}
+
+/// Stores `value` only if the current value equals `expected`, a `null` expectation requires
+/// the key to be absent. Returns whether the value was stored.
+pub async fn kv_cas(
+    client: &Client,
+    namespace: &str,
+    key: &str,
+    expected: &Value,
+    value: &Value,
+    ttl_ms: Option<i64>,
+) -> Result<bool> {
+    let rows_affected = match expected {
+        Value::Null => {
+            client
+                .execute(
+                    "INSERT INTO kv (namespace, key, value, expire_at) \
+                     VALUES ($1, $2, $3, now() + $4::BIGINT * INTERVAL '1 millisecond') \
+                     ON CONFLICT (namespace, key) DO UPDATE \
+                     SET value = EXCLUDED.value, expire_at = EXCLUDED.expire_at, update_at = now() \
+                     WHERE kv.expire_at IS NOT NULL AND kv.expire_at <= now()",
+                    &[&namespace, &key, &value, &ttl_ms],
+                )
+                .await?
+        }
+        expected => {
+            client
+                .execute(
+                    &format!(
+                        "UPDATE kv SET value = $3, \
+                         expire_at = now() + $4::BIGINT * INTERVAL '1 millisecond', update_at = now() \
+                         WHERE namespace = $1 AND key = $2 AND value = $5 AND {}",
+                        LIVE
+                    ),
+                    &[&namespace, &key, &value, &ttl_ms, &expected],
+                )
+                .await?
+        }
+    };
+    Ok(rows_affected > 0)
+}
+
+pub async fn list_namespaces(client: &Client) -> Result<Vec<KvNamespace>> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT namespace, COUNT(*) AS keys FROM kv WHERE {} GROUP BY namespace ORDER BY namespace",
+                LIVE
+            ),
+            &[],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_kv_namespace).collect())
+}
+
+pub async fn list_entries(
+    client: &Client,
+    namespace: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<KvEntry>> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT namespace, key, value, expire_at, update_at FROM kv \
+                 WHERE namespace = $1 AND {} ORDER BY key LIMIT $2 OFFSET $3",
+                LIVE
+            ),
+            &[&namespace, &limit, &offset],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_kv_entry).collect())
+}
+
+/// Removes every entry of a namespace, returning how many were removed.
+pub async fn clear_namespace(client: &Client, namespace: &str) -> Result<u64> {
+    let rows_affected = client
+        .execute("DELETE FROM kv WHERE namespace = $1", &[&namespace])
+        .await?;
+    Ok(rows_affected)
+}
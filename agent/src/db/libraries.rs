@@ -0,0 +1,67 @@
+use crate::db::models::Library;
+use crate::db::utils::row_to_library;
+use anyhow::Result;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+pub async fn create_library(client: &Client, name: &str, source: &str) -> Result<Library> {
+    let row = client
+        .query_one(
+            "INSERT INTO libraries (name, source) VALUES ($1, $2) RETURNING id, name, source, create_at, update_at",
+            &[&name, &source],
+        )
+        .await?;
+    Ok(row_to_library(row))
+}
+
+pub async fn get_library(client: &Client, id: Uuid) -> Result<Option<Library>> {
+    let row = client
+        .query_opt(
+            "SELECT id, name, source, create_at, update_at FROM libraries WHERE id = $1",
+            &[&id],
+        )
+        .await?;
+    Ok(row.map(row_to_library))
+}
+
+pub async fn get_library_by_name(client: &Client, name: &str) -> Result<Option<Library>> {
+    let row = client
+        .query_opt(
+            "SELECT id, name, source, create_at, update_at FROM libraries WHERE name = $1",
+            &[&name],
+        )
+        .await?;
+    Ok(row.map(row_to_library))
+}
+
+pub async fn list_libraries(client: &Client, limit: i64, offset: i64) -> Result<Vec<Library>> {
+    let rows = client
+        .query(
+            "SELECT id, name, source, create_at, update_at FROM libraries ORDER BY name ASC LIMIT $1 OFFSET $2",
+            &[&limit, &offset],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_library).collect())
+}
+
+pub async fn update_library(
+    client: &Client,
+    id: Uuid,
+    name: &str,
+    source: &str,
+) -> Result<Option<Library>> {
+    let row = client
+        .query_opt(
+            "UPDATE libraries SET name = $2, source = $3, update_at = now() WHERE id = $1 RETURNING id, name, source, create_at, update_at",
+            &[&id, &name, &source],
+        )
+        .await?;
+    Ok(row.map(row_to_library))
+}
+
+pub async fn delete_library(client: &Client, id: Uuid) -> Result<bool> {
+    let rows_affected = client
+        .execute("DELETE FROM libraries WHERE id = $1", &[&id])
+        .await?;
+    Ok(rows_affected > 0)
+}
@@ -1,33 +1,170 @@
 use crate::db::models::{Log, LogLevel};
 use crate::db::utils::row_to_log;
+use crate::events::{self, EventKind};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio_postgres::types::ToSql;
 use tokio_postgres::Client;
+use uuid::Uuid;
+
+/// Filters of the logs listed or counted, every filter that is set has to match.
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    /// Levels of the logs, any level when empty.
+    pub levels: Vec<String>,
+    pub category: Option<String>,
+    /// Start of the time range, inclusive.
+    pub from: Option<DateTime<Utc>>,
+    /// End of the time range, exclusive.
+    pub to: Option<DateTime<Utc>>,
+    /// Full-text search of the message, in the syntax of web search engines.
+    pub search: Option<String>,
+    pub pipeline_id: Option<Uuid>,
+    pub history_id: Option<Uuid>,
+}
+
+impl LogFilter {
+    // Adds the conditions of the filters that are set, binding their values to `params`
+    fn conditions<'a>(&'a self, params: &mut Vec<&'a (dyn ToSql + Sync)>) -> Vec<String> {
+        let mut conditions = vec![];
+        if !self.levels.is_empty() {
+            params.push(&self.levels);
+            conditions.push(format!("level::text = ANY(${})", params.len()));
+        }
+        if let Some(category) = &self.category {
+            params.push(category);
+            conditions.push(format!("category = ${}", params.len()));
+        }
+        if let Some(from) = &self.from {
+            params.push(from);
+            conditions.push(format!("create_at >= ${}", params.len()));
+        }
+        if let Some(to) = &self.to {
+            params.push(to);
+            conditions.push(format!("create_at < ${}", params.len()));
+        }
+        if let Some(search) = &self.search {
+            params.push(search);
+            conditions.push(format!(
+                "to_tsvector('simple', message) @@ websearch_to_tsquery('simple', ${})",
+                params.len()
+            ));
+        }
+        if let Some(pipeline_id) = &self.pipeline_id {
+            params.push(pipeline_id);
+            conditions.push(format!("pipeline_id = ${}", params.len()));
+        }
+        if let Some(history_id) = &self.history_id {
+            params.push(history_id);
+            conditions.push(format!("history_id = ${}", params.len()));
+        }
+        conditions
+    }
+}
+
+/// Position of the last log of a page, the next page starts with the log right after it.
+#[derive(Debug, Clone, Copy)]
+pub struct LogCursor {
+    pub create_at: DateTime<Utc>,
+    pub id: i32,
+}
+
+impl LogCursor {
+    pub fn of(log: &Log) -> Self {
+        LogCursor {
+            create_at: log.create_at,
+            id: log.id,
+        }
+    }
+
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let (create_at, id) = cursor.split_once(':')?;
+        Some(LogCursor {
+            create_at: DateTime::from_timestamp_micros(create_at.parse().ok()?)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for LogCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.create_at.timestamp_micros(), self.id)
+    }
+}
 
 pub async fn create_log(client: &Client, level: LogLevel, category: &str, message: &str) -> Result<Log> {
+    insert_log(client, level, category, message, None, None).await
+}
+
+/// Creates a log of a pipeline run, `history_id` is the history of the run once it has one.
+pub async fn create_pipeline_log(
+    client: &Client,
+    level: LogLevel,
+    category: &str,
+    message: &str,
+    pipeline_id: Uuid,
+    history_id: Option<Uuid>,
+) -> Result<Log> {
+    insert_log(client, level, category, message, Some(pipeline_id), history_id).await
+}
+
+async fn insert_log(
+    client: &Client,
+    level: LogLevel,
+    category: &str,
+    message: &str,
+    pipeline_id: Option<Uuid>,
+    history_id: Option<Uuid>,
+) -> Result<Log> {
     let row = client
         .query_one(
-            "INSERT INTO logs (level, category, message) VALUES ($1, $2, $3) RETURNING id, level, category, message, create_at",
-            &[&level, &category, &message],
+            "INSERT INTO logs (level, category, message, pipeline_id, history_id) VALUES ($1, $2, $3, $4, $5) RETURNING id, level, category, message, create_at, pipeline_id, history_id",
+            &[&level, &category, &message, &pipeline_id, &history_id],
         )
         .await?;
-    Ok(row_to_log(row))
+    let log = row_to_log(row);
+    events::publish(log.pipeline_id, log.history_id, EventKind::Log { log: log.clone() });
+    Ok(log)
 }
 
 pub async fn get_log(client: &Client, id: i32) -> Result<Option<Log>> {
     let row = client
         .query_opt(
-            "SELECT id, level, category, message, create_at FROM logs WHERE id = $1",
+            "SELECT id, level, category, message, create_at, pipeline_id, history_id FROM logs WHERE id = $1",
             &[&id],
         )
         .await?;
     Ok(row.map(row_to_log))
 }
 
-pub async fn list_logs(client: &Client, limit: i64, offset: i64) -> Result<Vec<Log>> {
+/// Lists the logs matching `filter` from the newest, starting after `cursor` when given.
+pub async fn list_logs(
+    client: &Client,
+    filter: &LogFilter,
+    cursor: Option<LogCursor>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Log>> {
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+    let mut conditions = filter.conditions(&mut params);
+    let cursor = cursor.map(|cursor| (cursor.create_at, cursor.id));
+    if let Some((create_at, id)) = &cursor {
+        params.push(create_at);
+        params.push(id);
+        conditions.push(format!("(create_at, id) < (${}, ${})", params.len() - 1, params.len()));
+    }
+    params.push(&limit);
+    params.push(&offset);
+
     let rows = client
         .query(
-            "SELECT id, level, category, message, create_at FROM logs ORDER BY create_at DESC LIMIT $1 OFFSET $2",
-            &[&limit, &offset],
+            &format!(
+                "SELECT id, level, category, message, create_at, pipeline_id, history_id FROM logs {} ORDER BY create_at DESC, id DESC LIMIT ${} OFFSET ${}",
+                where_clause(&conditions),
+                params.len() - 1,
+                params.len()
+            ),
+            &params,
         )
         .await?;
     Ok(rows.into_iter().map(row_to_log).collect())
@@ -40,7 +177,21 @@ pub async fn delete_log(client: &Client, id: i32) -> Result<bool> {
     Ok(rows_affected > 0)
 }
 
-pub async fn count_logs(client: &Client) -> Result<i64> {
-    let row = client.query_one("SELECT COUNT(*) FROM logs", &[]).await?;
+pub async fn count_logs(client: &Client, filter: &LogFilter) -> Result<i64> {
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+    let conditions = filter.conditions(&mut params);
+    let row = client
+        .query_one(
+            &format!("SELECT COUNT(*) FROM logs {}", where_clause(&conditions)),
+            &params,
+        )
+        .await?;
     Ok(row.get(0))
 }
+
+fn where_clause(conditions: &[String]) -> String {
+    match conditions.is_empty() {
+        true => String::new(),
+        false => format!("WHERE {}", conditions.join(" AND ")),
+    }
+}
@@ -6,6 +6,11 @@ pub mod pipelines;
 pub mod utils;
 pub mod history;
 pub mod routes;
+pub mod secrets;
+pub mod environments;
+pub mod kv;
+pub mod libraries;
+pub mod test_cases;
 
 pub use logs::*;
 pub use models::LogLevel;
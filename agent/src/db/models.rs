@@ -25,7 +25,7 @@ impl<'a> ToSql for LogLevel {
             LogLevel::Warn => "warn",
             LogLevel::Error => "error",
         }
-            .to_sql(ty, out)
+        .to_sql(ty, out)
     }
 
     fn accepts(ty: &Type) -> bool {
@@ -59,6 +59,10 @@ pub struct Log {
     pub category: String,
     pub message: String,
     pub create_at: DateTime<Utc>,
+    /// Pipeline whose run wrote the log.
+    pub pipeline_id: Option<Uuid>,
+    /// History of the run that wrote the log.
+    pub history_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -70,15 +74,28 @@ pub struct Node {
     pub script: String,
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
+    pub config_schema: Option<serde_json::Value>,
+    /// Schemas of the input ports keyed by port name, either a JSON Schema or a type name.
+    pub input_schemas: Option<serde_json::Value>,
+    /// Schemas of the output ports keyed by port name, either a JSON Schema or a type name.
+    pub output_schemas: Option<serde_json::Value>,
+    /// Language of the script, `javascript` or `typescript`.
+    pub language: String,
 }
 
 pub type HttpMethod = String;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Pipeline {
     pub id: Uuid,
     pub name: String,
     pub content: serde_json::Value,
+    pub error_handler_id: Option<Uuid>,
+    /// Hosts the HTTP node of this pipeline may call, `*.example.com` also matches subdomains.
+    pub allowed_hosts: Vec<String>,
+    /// Network restrictions of the scripts and HTTP nodes of this pipeline.
+    pub network_policy: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -91,6 +108,16 @@ pub struct History {
     pub end_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
     pub result: Option<serde_json::Value>,
+    pub trace: Option<serde_json::Value>,
+    /// Body of the request the run handled.
+    pub input: Option<serde_json::Value>,
+    /// Context of the run, the `environmentId` of the environment it ran in. The variables of the
+    /// environment are not recorded.
+    pub context: Option<serde_json::Value>,
+    /// The pipeline as it was when it ran.
+    pub revision: Option<serde_json::Value>,
+    /// History of the run this run replayed.
+    pub replay_of: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -100,4 +127,76 @@ pub struct Route {
     pub pipeline_id: Uuid,
     pub path: String,
     pub method: HttpMethod,
+    /// Environment whose variables the pipeline sees when run through this route.
+    pub environment_id: Option<Uuid>,
+}
+
+/// A named set of variables, exposed to node scripts as `env`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    pub id: Uuid,
+    pub name: String,
+    pub variables: serde_json::Value,
+}
+
+/// A stored secret, its encrypted value never leaves the database layer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Secret {
+    pub id: Uuid,
+    pub name: String,
+    pub create_at: DateTime<Utc>,
+    pub update_at: DateTime<Utc>,
+}
+
+/// A value of the key-value store available to node scripts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct KvEntry {
+    pub namespace: String,
+    pub key: String,
+    pub value: serde_json::Value,
+    pub expire_at: Option<DateTime<Utc>>,
+    pub update_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct KvNamespace {
+    pub namespace: String,
+    pub keys: i64,
+}
+
+/// A shared ES module that node scripts import by name.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Library {
+    pub id: Uuid,
+    pub name: String,
+    pub source: String,
+    pub create_at: DateTime<Utc>,
+    pub update_at: DateTime<Utc>,
+}
+
+/// A stored test case of either a node or a pipeline.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+    pub id: Uuid,
+    pub node_id: Option<Uuid>,
+    pub pipeline_id: Option<Uuid>,
+    pub name: String,
+    /// Input of the pipeline, or the inputs of the node keyed by port.
+    pub input: serde_json::Value,
+    /// Config of the node, unused by pipeline test cases.
+    pub config: Option<serde_json::Value>,
+    /// Outputs of graph nodes keyed by graph node id, returned by those nodes instead of running
+    /// them.
+    pub mocks: Option<serde_json::Value>,
+    /// Expectations on the result of the pipeline or the outputs of the node.
+    pub expect: serde_json::Value,
+    /// Environment whose variables the pipeline sees when the test case runs.
+    pub environment_id: Option<Uuid>,
+    pub create_at: DateTime<Utc>,
 }
@@ -4,17 +4,22 @@ use anyhow::Result;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_node(
     client: &Client,
     name: &str,
     script: &str,
     inputs: &[String],
     outputs: &[String],
+    config_schema: Option<&serde_json::Value>,
+    input_schemas: Option<&serde_json::Value>,
+    output_schemas: Option<&serde_json::Value>,
+    language: &str,
 ) -> Result<Node> {
     let row = client
         .query_one(
-            "INSERT INTO nodes (name, script, inputs, outputs) VALUES ($1, $2, $3, $4) RETURNING id, is_internal, name, script, inputs, outputs",
-            &[&name, &script, &inputs, &outputs],
+            "INSERT INTO nodes (name, script, inputs, outputs, config_schema, input_schemas, output_schemas, language) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id, is_internal, name, script, inputs, outputs, config_schema, input_schemas, output_schemas, language",
+            &[&name, &script, &inputs, &outputs, &config_schema, &input_schemas, &output_schemas, &language],
         )
         .await?;
     Ok(row_to_node(row))
@@ -23,13 +28,14 @@ pub async fn create_node(
 pub async fn get_node(client: &Client, id: Uuid) -> Result<Option<Node>> {
     let row = client
         .query_opt(
-            "SELECT id, is_internal, name, script, inputs, outputs FROM nodes WHERE id = $1",
+            "SELECT id, is_internal, name, script, inputs, outputs, config_schema, input_schemas, output_schemas, language FROM nodes WHERE id = $1",
             &[&id],
         )
         .await?;
     Ok(row.map(row_to_node))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_node(
     client: &Client,
     id: Uuid,
@@ -37,11 +43,15 @@ pub async fn update_node(
     script: &str,
     inputs: &[String],
     outputs: &[String],
+    config_schema: Option<&serde_json::Value>,
+    input_schemas: Option<&serde_json::Value>,
+    output_schemas: Option<&serde_json::Value>,
+    language: &str,
 ) -> Result<Option<Node>> {
     let row = client
         .query_opt(
-            "UPDATE nodes SET name = $2, script = $3, inputs = $4, outputs = $5 WHERE id = $1 RETURNING id, is_internal, name, script, inputs, outputs",
-            &[&id, &name, &script, &inputs, &outputs],
+            "UPDATE nodes SET name = $2, script = $3, inputs = $4, outputs = $5, config_schema = $6, input_schemas = $7, output_schemas = $8, language = $9 WHERE id = $1 RETURNING id, is_internal, name, script, inputs, outputs, config_schema, input_schemas, output_schemas, language",
+            &[&id, &name, &script, &inputs, &outputs, &config_schema, &input_schemas, &output_schemas, &language],
         )
         .await?;
     Ok(row.map(row_to_node))
@@ -57,7 +67,7 @@ pub async fn delete_node(client: &Client, id: Uuid) -> Result<bool> {
 pub async fn list_nodes(client: &Client, limit: i64, offset: i64) -> Result<Vec<Node>> {
     let rows = client
         .query(
-            "SELECT id, is_internal, name, script, inputs, outputs FROM nodes ORDER BY name ASC LIMIT $1 OFFSET $2",
+            "SELECT id, is_internal, name, script, inputs, outputs, config_schema, input_schemas, output_schemas, language FROM nodes ORDER BY name ASC LIMIT $1 OFFSET $2",
             &[&limit, &offset],
         )
         .await?;
@@ -68,3 +78,30 @@ pub async fn count_nodes(client: &Client) -> Result<i64> {
     let row = client.query_one("SELECT COUNT(*) FROM nodes", &[]).await?;
     Ok(row.get(0))
 }
+
+/// Inserts or refreshes the row of a built-in node, matched by its name.
+pub async fn upsert_internal_node(
+    client: &Client,
+    name: &str,
+    inputs: &[String],
+    outputs: &[String],
+    config_schema: Option<&serde_json::Value>,
+    input_schemas: Option<&serde_json::Value>,
+    output_schemas: Option<&serde_json::Value>,
+) -> Result<()> {
+    let updated = client
+        .execute(
+            "UPDATE nodes SET inputs = $2, outputs = $3, config_schema = $4, input_schemas = $5, output_schemas = $6 WHERE name = $1 AND is_internal = TRUE",
+            &[&name, &inputs, &outputs, &config_schema, &input_schemas, &output_schemas],
+        )
+        .await?;
+    if updated == 0 {
+        client
+            .execute(
+                "INSERT INTO nodes (name, is_internal, script, inputs, outputs, config_schema, input_schemas, output_schemas) VALUES ($1, TRUE, 'internal', $2, $3, $4, $5, $6)",
+                &[&name, &inputs, &outputs, &config_schema, &input_schemas, &output_schemas],
+            )
+            .await?;
+    }
+    Ok(())
+}
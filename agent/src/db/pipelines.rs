@@ -8,11 +8,14 @@ pub async fn create_pipeline(
     client: &Client,
     name: &str,
     content: &serde_json::Value,
+    error_handler_id: Option<Uuid>,
+    allowed_hosts: &[String],
+    network_policy: Option<&serde_json::Value>,
 ) -> Result<Pipeline> {
     let row = client
         .query_one(
-            "INSERT INTO pipelines (name, content) VALUES ($1, $2) RETURNING id, name, content",
-            &[&name, &content],
+            "INSERT INTO pipelines (name, content, error_handler_id, allowed_hosts, network_policy) VALUES ($1, $2, $3, $4, $5) RETURNING id, name, content, error_handler_id, allowed_hosts, network_policy",
+            &[&name, &content, &error_handler_id, &allowed_hosts, &network_policy],
         )
         .await?;
     Ok(row_to_pipeline(row))
@@ -21,7 +24,7 @@ pub async fn create_pipeline(
 pub async fn get_pipeline(client: &Client, id: Uuid) -> Result<Option<Pipeline>> {
     let row = client
         .query_opt(
-            "SELECT id, name, content FROM pipelines WHERE id = $1",
+            "SELECT id, name, content, error_handler_id, allowed_hosts, network_policy FROM pipelines WHERE id = $1",
             &[&id],
         )
         .await?;
@@ -33,11 +36,14 @@ pub async fn update_pipeline(
     id: Uuid,
     name: &str,
     content: &serde_json::Value,
+    error_handler_id: Option<Uuid>,
+    allowed_hosts: &[String],
+    network_policy: Option<&serde_json::Value>,
 ) -> Result<Option<Pipeline>> {
     let row = client
         .query_opt(
-            "UPDATE pipelines SET name = $2, content = $3 WHERE id = $1 RETURNING id, name, content",
-            &[&id, &name, &content],
+            "UPDATE pipelines SET name = $2, content = $3, error_handler_id = $4, allowed_hosts = $5, network_policy = $6 WHERE id = $1 RETURNING id, name, content, error_handler_id, allowed_hosts, network_policy",
+            &[&id, &name, &content, &error_handler_id, &allowed_hosts, &network_policy],
         )
         .await?;
     Ok(row.map(row_to_pipeline))
@@ -53,7 +59,7 @@ pub async fn delete_pipeline(client: &Client, id: Uuid) -> Result<bool> {
 pub async fn list_pipelines(client: &Client, limit: i64, offset: i64) -> Result<Vec<Pipeline>> {
     let rows = client
         .query(
-            "SELECT id, name, content FROM pipelines ORDER BY name ASC LIMIT $1 OFFSET $2",
+            "SELECT id, name, content, error_handler_id, allowed_hosts, network_policy FROM pipelines ORDER BY name ASC LIMIT $1 OFFSET $2",
             &[&limit, &offset],
         )
         .await?;
@@ -4,11 +4,11 @@ use anyhow::Result;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
-pub async fn create_route(client: &Client, pipeline_id: Uuid, path: &str, method: &str) -> Result<Route> {
+pub async fn create_route(client: &Client, pipeline_id: Uuid, path: &str, method: &str, environment_id: Option<Uuid>) -> Result<Route> {
     // check if there is already a route with the same path and method
     let existing_route = client
         .query_opt(
-            "SELECT id, pipeline_id, path, method FROM routes WHERE path = $1 AND method = $2",
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes WHERE path = $1 AND method = $2",
             &[&path, &method],
         )
         .await?;
@@ -18,8 +18,8 @@ pub async fn create_route(client: &Client, pipeline_id: Uuid, path: &str, method
     
     let row = client
         .query_one(
-            "INSERT INTO routes (pipeline_id, path, method) VALUES ($1, $2, $3) RETURNING id, pipeline_id, path, method",
-            &[&pipeline_id, &path, &method],
+            "INSERT INTO routes (pipeline_id, path, method, environment_id) VALUES ($1, $2, $3, $4) RETURNING id, pipeline_id, path, method, environment_id",
+            &[&pipeline_id, &path, &method, &environment_id],
         )
         .await?;
     Ok(row_to_route(row))
@@ -28,7 +28,7 @@ pub async fn create_route(client: &Client, pipeline_id: Uuid, path: &str, method
 pub async fn get_route(client: &Client, id: Uuid) -> Result<Option<Route>> {
     let row = client
         .query_opt(
-            "SELECT id, pipeline_id, path, method FROM routes WHERE id = $1",
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes WHERE id = $1",
             &[&id],
         )
         .await?;
@@ -38,7 +38,7 @@ pub async fn get_route(client: &Client, id: Uuid) -> Result<Option<Route>> {
 pub async fn list_routes(client: &Client, limit: i64, offset: i64) -> Result<Vec<Route>> {
     let rows = client
         .query(
-            "SELECT id, pipeline_id, path, method FROM routes ORDER BY path LIMIT $1 OFFSET $2",
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes ORDER BY path LIMIT $1 OFFSET $2",
             &[&limit, &offset],
         )
         .await?;
@@ -60,19 +60,19 @@ pub async fn count_routes(client: &Client) -> Result<i64> {
 pub async fn get_routes_by_pipeline_id(client: &Client, pipeline_id: Uuid) -> Result<Vec<Route>> {
     let rows = client
         .query(
-            "SELECT id, pipeline_id, path, method FROM routes WHERE pipeline_id = $1",
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes WHERE pipeline_id = $1",
             &[&pipeline_id],
         )
         .await?;
     Ok(rows.into_iter().map(row_to_route).collect())
 }
 
-pub async fn update_route(client: &Client, id: Uuid, path: &str, method: &str) -> Result<Route> {
-    // check if there is already a route with the same path and method
+pub async fn update_route(client: &Client, id: Uuid, path: &str, method: &str, environment_id: Option<Uuid>) -> Result<Route> {
+    // check if there is already another route with the same path and method
     let existing_route = client
         .query_opt(
-            "SELECT id, pipeline_id, path, method FROM routes WHERE path = $1 AND method = $2",
-            &[&path, &method],
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes WHERE path = $1 AND method = $2 AND id <> $3",
+            &[&path, &method, &id],
         )
         .await?;
     if existing_route.is_some() {
@@ -81,8 +81,8 @@ pub async fn update_route(client: &Client, id: Uuid, path: &str, method: &str) -
     
     let row = client
         .query_one(
-            "UPDATE routes SET path = $2, method = $3 WHERE id = $1 RETURNING id, pipeline_id, path, method",
-            &[&id, &path, &method],
+            "UPDATE routes SET path = $2, method = $3, environment_id = $4 WHERE id = $1 RETURNING id, pipeline_id, path, method, environment_id",
+            &[&id, &path, &method, &environment_id],
         )
         .await?;
     Ok(row_to_route(row))
@@ -91,7 +91,7 @@ pub async fn update_route(client: &Client, id: Uuid, path: &str, method: &str) -
 pub async fn find_route(client: &Client, path: String, method: String) -> Result<Route> {
     let row = client
         .query_one(
-            "SELECT id, pipeline_id, path, method FROM routes WHERE path = $1 AND method = $2",
+            "SELECT id, pipeline_id, path, method, environment_id FROM routes WHERE path = $1 AND method = $2",
             &[&path, &method],
         )
         .await?;
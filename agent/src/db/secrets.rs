@@ -0,0 +1,53 @@
+use crate::db::models::Secret;
+use crate::db::utils::row_to_secret;
+use anyhow::Result;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+pub async fn create_secret(client: &Client, name: &str, value: &[u8]) -> Result<Secret> {
+    let row = client
+        .query_one(
+            "INSERT INTO secrets (name, value) VALUES ($1, $2) RETURNING id, name, create_at, update_at",
+            &[&name, &value],
+        )
+        .await?;
+    Ok(row_to_secret(row))
+}
+
+pub async fn update_secret(client: &Client, id: Uuid, value: &[u8]) -> Result<Option<Secret>> {
+    let row = client
+        .query_opt(
+            "UPDATE secrets SET value = $2, update_at = now() WHERE id = $1 RETURNING id, name, create_at, update_at",
+            &[&id, &value],
+        )
+        .await?;
+    Ok(row.map(row_to_secret))
+}
+
+pub async fn delete_secret(client: &Client, id: Uuid) -> Result<bool> {
+    let rows_affected = client
+        .execute("DELETE FROM secrets WHERE id = $1", &[&id])
+        .await?;
+    Ok(rows_affected > 0)
+}
+
+pub async fn list_secrets(client: &Client, limit: i64, offset: i64) -> Result<Vec<Secret>> {
+    let rows = client
+        .query(
+            "SELECT id, name, create_at, update_at FROM secrets ORDER BY name ASC LIMIT $1 OFFSET $2",
+            &[&limit, &offset],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_secret).collect())
+}
+
+/// Names and encrypted values of the secrets named in `names`, for decryption by the executor.
+pub async fn get_secret_values(client: &Client, names: &[String]) -> Result<Vec<(String, Vec<u8>)>> {
+    let rows = client
+        .query("SELECT name, value FROM secrets WHERE name = ANY($1)", &[&names])
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get("name"), row.get("value")))
+        .collect())
+}
@@ -0,0 +1,113 @@
+use crate::db::models::TestCase;
+use crate::db::utils::row_to_test_case;
+use anyhow::Result;
+use serde_json::Value;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+const TEST_CASE_COLUMNS: &str =
+    "id, node_id, pipeline_id, name, input, config, mocks, expect, environment_id, create_at";
+
+/// Fields of a test case as given when it is created or updated.
+pub struct TestCaseFields<'a> {
+    pub node_id: Option<Uuid>,
+    pub pipeline_id: Option<Uuid>,
+    pub name: &'a str,
+    pub input: &'a Value,
+    pub config: Option<&'a Value>,
+    pub mocks: Option<&'a Value>,
+    pub expect: &'a Value,
+    pub environment_id: Option<Uuid>,
+}
+
+pub async fn create_test_case(client: &Client, fields: &TestCaseFields<'_>) -> Result<TestCase> {
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO test_cases (node_id, pipeline_id, name, input, config, mocks, expect, environment_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {}",
+                TEST_CASE_COLUMNS
+            ),
+            &[
+                &fields.node_id,
+                &fields.pipeline_id,
+                &fields.name,
+                &fields.input,
+                &fields.config,
+                &fields.mocks,
+                &fields.expect,
+                &fields.environment_id,
+            ],
+        )
+        .await?;
+    Ok(row_to_test_case(row))
+}
+
+pub async fn get_test_case(client: &Client, id: Uuid) -> Result<Option<TestCase>> {
+    let row = client
+        .query_opt(
+            &format!("SELECT {} FROM test_cases WHERE id = $1", TEST_CASE_COLUMNS),
+            &[&id],
+        )
+        .await?;
+    Ok(row.map(row_to_test_case))
+}
+
+pub async fn list_node_test_cases(client: &Client, node_id: Uuid) -> Result<Vec<TestCase>> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM test_cases WHERE node_id = $1 ORDER BY name ASC",
+                TEST_CASE_COLUMNS
+            ),
+            &[&node_id],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_test_case).collect())
+}
+
+pub async fn list_pipeline_test_cases(client: &Client, pipeline_id: Uuid) -> Result<Vec<TestCase>> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM test_cases WHERE pipeline_id = $1 ORDER BY name ASC",
+                TEST_CASE_COLUMNS
+            ),
+            &[&pipeline_id],
+        )
+        .await?;
+    Ok(rows.into_iter().map(row_to_test_case).collect())
+}
+
+pub async fn update_test_case(
+    client: &Client,
+    id: Uuid,
+    fields: &TestCaseFields<'_>,
+) -> Result<Option<TestCase>> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE test_cases SET node_id = $2, pipeline_id = $3, name = $4, input = $5, config = $6, mocks = $7, expect = $8, environment_id = $9 WHERE id = $1 RETURNING {}",
+                TEST_CASE_COLUMNS
+            ),
+            &[
+                &id,
+                &fields.node_id,
+                &fields.pipeline_id,
+                &fields.name,
+                &fields.input,
+                &fields.config,
+                &fields.mocks,
+                &fields.expect,
+                &fields.environment_id,
+            ],
+        )
+        .await?;
+    Ok(row.map(row_to_test_case))
+}
+
+pub async fn delete_test_case(client: &Client, id: Uuid) -> Result<bool> {
+    let rows_affected = client
+        .execute("DELETE FROM test_cases WHERE id = $1", &[&id])
+        .await?;
+    Ok(rows_affected > 0)
+}
@@ -1,4 +1,7 @@
-use crate::db::models::{History, Log, Node, Pipeline, Route};
+use crate::db::models::{
+    Environment, History, KvEntry, KvNamespace, Library, Log, Node, Pipeline, Route, Secret,
+    TestCase,
+};
 use tokio_postgres::Row;
 
 // Helper functions to convert database rows to structs
@@ -9,6 +12,8 @@ pub fn row_to_log(row: Row) -> Log {
         category: row.get(2),
         message: row.get(3),
         create_at: row.get(4),
+        pipeline_id: row.get(5),
+        history_id: row.get(6),
     }
 }
 
@@ -20,6 +25,10 @@ pub fn row_to_node(row: Row) -> Node {
         script: row.get(3),
         inputs: row.get(4),
         outputs: row.get(5),
+        config_schema: row.get(6),
+        input_schemas: row.get(7),
+        output_schemas: row.get(8),
+        language: row.get(9),
     }
 }
 
@@ -28,6 +37,9 @@ pub fn row_to_pipeline(row: Row) -> Pipeline {
         id: row.get(0),
         name: row.get(1),
         content: row.get(2),
+        error_handler_id: row.get(3),
+        allowed_hosts: row.get(4),
+        network_policy: row.get(5),
     }
 }
 
@@ -40,6 +52,11 @@ pub fn row_to_history(row: Row) -> History {
         end_at: row.get("end_at"),
         error: row.get("error"),
         result: row.get("result"),
+        trace: row.get("trace"),
+        input: row.get("input"),
+        context: row.get("context"),
+        revision: row.get("revision"),
+        replay_of: row.get("replay_of"),
     }
 }
 
@@ -49,5 +66,65 @@ pub fn row_to_route(row: Row) -> Route {
         pipeline_id: row.get("pipeline_id"),
         path: row.get("path"),
         method: row.get("method"),
+        environment_id: row.get("environment_id"),
+    }
+}
+
+pub fn row_to_environment(row: Row) -> Environment {
+    Environment {
+        id: row.get("id"),
+        name: row.get("name"),
+        variables: row.get("variables"),
+    }
+}
+
+pub fn row_to_secret(row: Row) -> Secret {
+    Secret {
+        id: row.get("id"),
+        name: row.get("name"),
+        create_at: row.get("create_at"),
+        update_at: row.get("update_at"),
+    }
+}
+
+pub fn row_to_kv_entry(row: Row) -> KvEntry {
+    KvEntry {
+        namespace: row.get("namespace"),
+        key: row.get("key"),
+        value: row.get("value"),
+        expire_at: row.get("expire_at"),
+        update_at: row.get("update_at"),
+    }
+}
+
+pub fn row_to_kv_namespace(row: Row) -> KvNamespace {
+    KvNamespace {
+        namespace: row.get("namespace"),
+        keys: row.get("keys"),
+    }
+}
+
+pub fn row_to_library(row: Row) -> Library {
+    Library {
+        id: row.get("id"),
+        name: row.get("name"),
+        source: row.get("source"),
+        create_at: row.get("create_at"),
+        update_at: row.get("update_at"),
+    }
+}
+
+pub fn row_to_test_case(row: Row) -> TestCase {
+    TestCase {
+        id: row.get("id"),
+        node_id: row.get("node_id"),
+        pipeline_id: row.get("pipeline_id"),
+        name: row.get("name"),
+        input: row.get("input"),
+        config: row.get("config"),
+        mocks: row.get("mocks"),
+        expect: row.get("expect"),
+        environment_id: row.get("environment_id"),
+        create_at: row.get("create_at"),
     }
 }
@@ -0,0 +1,420 @@
+use crate::db::environments::get_environment;
+use crate::db::models::Pipeline;
+use crate::executor::{CombinedNode, GraphExecutor};
+use crate::routes::exec::{
+    error_json, pipeline_executor, remove_scratch_namespace, scratch_namespace,
+};
+use crate::validation::validate_pipeline;
+use actix_ws::{Message, MessageStream, ProtocolError, Session};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+/// A command sent by the client of a debug session.
+#[derive(Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum Command {
+    /// Starts a run paused before its first node. `content` replaces the saved graph of the
+    /// pipeline, e.g. while it is being edited. Scripts use a scratch `kv` namespace unless
+    /// `use_pipeline_kv` opts in to the one of the pipeline.
+    Start {
+        #[serde(default)]
+        input: Value,
+        content: Option<Value>,
+        #[serde(default)]
+        breakpoints: Vec<String>,
+        #[serde(default)]
+        mocks: HashMap<String, Value>,
+        environment_id: Option<Uuid>,
+        #[serde(default)]
+        use_pipeline_kv: bool,
+    },
+    SetBreakpoints {
+        breakpoints: Vec<String>,
+    },
+    /// Runs the next node and pauses again.
+    Step,
+    /// Runs until the next node has a breakpoint or the run ends.
+    Continue,
+    /// Sends the values in the data cache.
+    Inspect,
+    /// Replaces a value in the data cache.
+    SetValue {
+        node_id: String,
+        port: String,
+        value: Value,
+    },
+    Stop,
+}
+
+/// An event sent to the client of a debug session.
+#[derive(Serialize)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum Event {
+    /// The run is paused before `node_id`.
+    Paused {
+        node_id: String,
+        node_name: String,
+        reason: &'static str,
+    },
+    /// A node ran, one event per attempt.
+    Step {
+        step: Value,
+    },
+    Data {
+        values: Value,
+    },
+    Finished {
+        result: Value,
+        trace: Value,
+    },
+    Failed {
+        error: Value,
+        trace: Value,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// A run driven one node at a time. The executor runs a queue of nodes per round, the nodes of a
+// round are run one by one and the next round is scheduled once all of them ran.
+struct DebugRun {
+    executor: GraphExecutor,
+    breakpoints: HashSet<String>,
+    round: Vec<CombinedNode>,
+    pending: VecDeque<CombinedNode>,
+    /// The `kv` namespace of the run when it is not the one of the pipeline, removed by `end`.
+    scratch_namespace: Option<String>,
+}
+
+// How a run continues after running a node
+enum Outcome {
+    Paused,
+    Finished(Value),
+    Failed(Value),
+}
+
+impl DebugRun {
+    fn next_node(&self) -> Option<&CombinedNode> {
+        self.pending.front()
+    }
+
+    // Runs the next node, scheduling the next round when it was the last of its round
+    async fn step(&mut self, session: &mut Session) -> Outcome {
+        let Some(node) = self.pending.pop_front() else {
+            return Outcome::Failed(json!({ "message": "Run has no node left to run" }));
+        };
+
+        let trace_len = self.executor.trace.len();
+        self.executor.current_node_queue = vec![node];
+        let result = self.executor.exec_current_queue().await;
+        let steps = self.executor.trace_json();
+        for step in steps.as_array().into_iter().flatten().skip(trace_len) {
+            send(session, &Event::Step { step: step.clone() }).await;
+        }
+        if let Err(e) = result {
+            return Outcome::Failed(self.executor.secrets().redact_value(error_json(&e)));
+        }
+        if !self.pending.is_empty() {
+            return Outcome::Paused;
+        }
+
+        self.executor.current_node_queue = std::mem::take(&mut self.round);
+        if let Err(e) = self.executor.update_next_node_queue() {
+            return Outcome::Failed(self.executor.secrets().redact_value(error_json(&e)));
+        }
+        self.executor.apply_next_queue();
+        if self.executor.reached_end {
+            return match self.executor.get_result() {
+                Ok(result) => Outcome::Finished(self.executor.secrets().redact_value(result)),
+                Err(e) => Outcome::Failed(self.executor.secrets().redact_value(error_json(&e))),
+            };
+        }
+        if self.executor.current_node_queue.is_empty() {
+            return Outcome::Failed(
+                json!({ "message": "Pipeline stopped before reaching EndRequest" }),
+            );
+        }
+
+        self.round = self.executor.current_node_queue.clone();
+        self.pending = self.round.clone().into();
+        Outcome::Paused
+    }
+}
+
+/// Serves a debug session of a pipeline over a WebSocket until the client leaves or stops it.
+/// Runs are not recorded in history.
+pub async fn serve(
+    client: Arc<Client>,
+    pipeline: Pipeline,
+    mut session: Session,
+    mut stream: MessageStream,
+) {
+    let mut run: Option<DebugRun> = None;
+
+    loop {
+        let command = match receive(&mut session, stream.recv().await).await {
+            Received::Command(command) => *command,
+            Received::Nothing => continue,
+            Received::Closed => break,
+        };
+
+        match command {
+            Command::Start {
+                input,
+                content,
+                breakpoints,
+                mocks,
+                environment_id,
+                use_pipeline_kv,
+            } => {
+                end(&client, run.take()).await;
+                let mut pipeline = pipeline.clone();
+                if let Some(content) = content {
+                    pipeline.content = content;
+                }
+                let started = start(
+                    &client,
+                    &pipeline,
+                    input,
+                    mocks,
+                    environment_id,
+                    use_pipeline_kv,
+                )
+                .await;
+                match started {
+                    Ok(mut started) => {
+                        started.breakpoints = breakpoints.into_iter().collect();
+                        pause(&mut session, &started, "start").await;
+                        run = Some(started);
+                    }
+                    Err(message) => send(&mut session, &Event::Error { message }).await,
+                }
+            }
+            Command::Stop => break,
+            command => {
+                let Some(current) = run.as_mut() else {
+                    let message = "No run is in progress, start one first".to_string();
+                    send(&mut session, &Event::Error { message }).await;
+                    continue;
+                };
+                let mut stopped = false;
+                let finished = match command {
+                    Command::SetBreakpoints { breakpoints } => {
+                        current.breakpoints = breakpoints.into_iter().collect();
+                        None
+                    }
+                    Command::Step => match current.step(&mut session).await {
+                        Outcome::Paused => {
+                            pause(&mut session, current, "step").await;
+                            None
+                        }
+                        outcome => Some(outcome),
+                    },
+                    Command::Continue => loop {
+                        match current.step(&mut session).await {
+                            Outcome::Paused => {
+                                if interrupted(&mut session, &mut stream, current).await {
+                                    stopped = true;
+                                    break None;
+                                }
+                                let at_breakpoint = current.next_node().is_some_and(|node| {
+                                    current.breakpoints.contains(&node.graph_node.id)
+                                });
+                                if at_breakpoint {
+                                    pause(&mut session, current, "breakpoint").await;
+                                    break None;
+                                }
+                            }
+                            outcome => break Some(outcome),
+                        }
+                    },
+                    Command::Inspect => {
+                        let values = current.executor.data_json();
+                        send(&mut session, &Event::Data { values }).await;
+                        None
+                    }
+                    Command::SetValue {
+                        node_id,
+                        port,
+                        value,
+                    } => {
+                        if let Err(e) = current.executor.set_data(&node_id, &port, value) {
+                            let message = e.to_string();
+                            send(&mut session, &Event::Error { message }).await;
+                        }
+                        None
+                    }
+                    Command::Start { .. } | Command::Stop => unreachable!(),
+                };
+
+                if let Some(outcome) = finished {
+                    let trace = current.executor.trace_json();
+                    let event = match outcome {
+                        Outcome::Finished(result) => Event::Finished { result, trace },
+                        Outcome::Failed(error) => Event::Failed { error, trace },
+                        Outcome::Paused => unreachable!(),
+                    };
+                    send(&mut session, &event).await;
+                    end(&client, run.take()).await;
+                }
+                if stopped {
+                    break;
+                }
+            }
+        }
+    }
+
+    end(&client, run.take()).await;
+    let _ = session.close(None).await;
+}
+
+// What a message of the client amounts to
+enum Received {
+    Command(Box<Command>),
+    /// Pings and invalid commands, which are answered already.
+    Nothing,
+    /// The client left or closed the session.
+    Closed,
+}
+
+async fn receive(
+    session: &mut Session,
+    message: Option<Result<Message, ProtocolError>>,
+) -> Received {
+    let text = match message {
+        Some(Ok(Message::Text(text))) => text,
+        Some(Ok(Message::Ping(bytes))) => {
+            let _ = session.pong(&bytes).await;
+            return Received::Nothing;
+        }
+        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Received::Closed,
+        Some(Ok(_)) => return Received::Nothing,
+    };
+    match serde_json::from_str::<Command>(&text) {
+        Ok(command) => Received::Command(Box::new(command)),
+        Err(e) => {
+            let message = format!("Invalid command: {}", e);
+            send(session, &Event::Error { message }).await;
+            Received::Nothing
+        }
+    }
+}
+
+// Handles the commands the client sent while a run continues, between two of its nodes.
+// Returns whether the client stopped the session.
+async fn interrupted(
+    session: &mut Session,
+    stream: &mut MessageStream,
+    run: &mut DebugRun,
+) -> bool {
+    while let Some(message) = stream.recv().now_or_never() {
+        let command = match receive(session, message).await {
+            Received::Command(command) => *command,
+            Received::Nothing => continue,
+            Received::Closed => return true,
+        };
+        match command {
+            Command::Stop => return true,
+            Command::SetBreakpoints { breakpoints } => {
+                run.breakpoints = breakpoints.into_iter().collect();
+            }
+            Command::Inspect => {
+                let values = run.executor.data_json();
+                send(session, &Event::Data { values }).await;
+            }
+            _ => {
+                let message = "The run is continuing, only setBreakpoints, inspect and stop are \
+                               handled until it pauses"
+                    .to_string();
+                send(session, &Event::Error { message }).await;
+            }
+        }
+    }
+    false
+}
+
+// Ends a run, removing its scratch namespace
+async fn end(client: &Client, run: Option<DebugRun>) {
+    if let Some(namespace) = run.and_then(|run| run.scratch_namespace) {
+        remove_scratch_namespace(client, &namespace).await;
+    }
+}
+
+// Prepares a run of the pipeline paused before its first node
+async fn start(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: Value,
+    mocks: HashMap<String, Value>,
+    environment_id: Option<Uuid>,
+    use_pipeline_kv: bool,
+) -> Result<DebugRun, String> {
+    let errors = validate_pipeline(client, &pipeline.content)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(error) = errors.first() {
+        return Err(error.message.clone());
+    }
+
+    let env = match environment_id {
+        Some(environment_id) => {
+            get_environment(client, environment_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Environment {} does not exist", environment_id))?
+                .variables
+        }
+        None => json!({}),
+    };
+
+    let mut executor = pipeline_executor(client, pipeline, &env)
+        .await
+        .map_err(|e| e.to_string())?;
+    let scratch_namespace = (!use_pipeline_kv).then(scratch_namespace);
+    if let Some(namespace) = &scratch_namespace {
+        executor.kv_namespace = namespace.clone();
+    }
+    executor.mocks = mocks;
+    executor.init_entry(input).map_err(|e| e.to_string())?;
+    executor.init_node_queue();
+
+    let round = executor.current_node_queue.clone();
+    Ok(DebugRun {
+        executor,
+        breakpoints: HashSet::new(),
+        pending: round.clone().into(),
+        round,
+        scratch_namespace,
+    })
+}
+
+async fn pause(session: &mut Session, run: &DebugRun, reason: &'static str) {
+    if let Some(node) = run.next_node() {
+        let event = Event::Paused {
+            node_id: node.graph_node.id.clone(),
+            node_name: node.db_node.name.clone(),
+            reason,
+        };
+        send(session, &event).await;
+    }
+}
+
+async fn send(session: &mut Session, event: &Event) {
+    if let Ok(text) = serde_json::to_string(event) {
+        let _ = session.text(text).await;
+    }
+}
@@ -0,0 +1,282 @@
+use crate::sandbox::{allowed_addresses, record_denial};
+use anyhow::{anyhow, Result};
+use deno_tls::{BasicAuth, Proxy};
+use log::warn;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// SOCKS5, RFC 1928 and RFC 1929
+const VERSION: u8 = 5;
+const PASSWORD_AUTH: u8 = 2;
+const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const AUTH_VERSION: u8 = 1;
+const CONNECT: u8 = 1;
+const SUCCEEDED: u8 = 0;
+const GENERAL_FAILURE: u8 = 1;
+const NOT_ALLOWED: u8 = 2;
+const HOST_UNREACHABLE: u8 = 4;
+const COMMAND_NOT_SUPPORTED: u8 = 7;
+const ADDRESS_NOT_SUPPORTED: u8 = 8;
+
+// Scripts connect through a SOCKS5 proxy listening on the loopback interface. `fetch` hands it
+// host names unresolved, the proxy resolves them, checks the addresses against the policies of
+// the run and connects to an address it checked.
+static PORT: LazyLock<Result<u16, String>> = LazyLock::new(start);
+
+// Runs connecting through the proxy keyed by the password they authenticate with
+static GATES: LazyLock<Mutex<HashMap<String, Gate>>> = LazyLock::new(Default::default);
+
+// The state of a run checked by the proxy, dropped along with the sandbox of the run
+#[derive(Clone)]
+struct Gate {
+    deny_private: Weak<AtomicBool>,
+    denials: Weak<Mutex<Vec<String>>>,
+}
+
+/// Registers the sandbox state of a run with the egress proxy and returns the proxy its
+/// `fetch` has to connect through.
+pub fn register(
+    deny_private: &Arc<AtomicBool>,
+    denials: &Arc<Mutex<Vec<String>>>,
+) -> Result<Proxy> {
+    let port = PORT
+        .as_ref()
+        .map_err(|e| anyhow!("Egress proxy is not available: {}", e))?;
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+
+    let mut gates = GATES.lock().unwrap();
+    gates.retain(|_, gate| gate.denials.strong_count() > 0);
+    gates.insert(
+        password.clone(),
+        Gate {
+            deny_private: Arc::downgrade(deny_private),
+            denials: Arc::downgrade(denials),
+        },
+    );
+
+    Ok(Proxy {
+        url: format!("socks5://127.0.0.1:{}", port),
+        basic_auth: Some(BasicAuth {
+            username: "run".to_string(),
+            password,
+        }),
+    })
+}
+
+// The proxy runs on a thread of its own, so it outlives the runtime of whichever request
+// started it
+fn start() -> Result<u16, String> {
+    let listener =
+        std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    std::thread::Builder::new()
+        .name("egress-proxy".to_string())
+        .spawn(move || {
+            runtime.block_on(async move {
+                let listener = match TcpListener::from_std(listener) {
+                    Ok(listener) => listener,
+                    Err(e) => return warn!("Egress proxy failed to start: {}", e),
+                };
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            tokio::spawn(async move {
+                                if let Err(e) = serve(stream).await {
+                                    warn!("Egress proxy connection failed: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Egress proxy failed to accept a connection: {}", e),
+                    }
+                }
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    Ok(port)
+}
+
+async fn serve(mut stream: TcpStream) -> io::Result<()> {
+    // Only password authentication is offered, the password identifies the run
+    let [version, count] = read_array(&mut stream).await?;
+    let mut methods = vec![0; count as usize];
+    stream.read_exact(&mut methods).await?;
+    if version != VERSION || !methods.contains(&PASSWORD_AUTH) {
+        return stream.write_all(&[VERSION, NO_ACCEPTABLE_METHOD]).await;
+    }
+    stream.write_all(&[VERSION, PASSWORD_AUTH]).await?;
+
+    let [_auth_version] = read_array(&mut stream).await?;
+    let _username = read_bytes(&mut stream).await?;
+    let password = String::from_utf8_lossy(&read_bytes(&mut stream).await?).into_owned();
+    let gate = GATES.lock().unwrap().get(&password).cloned();
+    let (Some(deny_private), Some(denials)) = (
+        gate.as_ref().and_then(|gate| gate.deny_private.upgrade()),
+        gate.as_ref().and_then(|gate| gate.denials.upgrade()),
+    ) else {
+        return stream.write_all(&[AUTH_VERSION, 1]).await;
+    };
+    stream.write_all(&[AUTH_VERSION, 0]).await?;
+
+    let [_, command, _, address_type] = read_array(&mut stream).await?;
+    let host = match address_type {
+        1 => Ipv4Addr::from(read_array::<4>(&mut stream).await?).to_string(),
+        3 => String::from_utf8_lossy(&read_bytes(&mut stream).await?).into_owned(),
+        4 => Ipv6Addr::from(read_array::<16>(&mut stream).await?).to_string(),
+        _ => return reply(&mut stream, ADDRESS_NOT_SUPPORTED).await,
+    };
+    let port = u16::from_be_bytes(read_array(&mut stream).await?);
+    if command != CONNECT {
+        return reply(&mut stream, COMMAND_NOT_SUPPORTED).await;
+    }
+
+    let addresses = match tokio::net::lookup_host((host.as_str(), port)).await {
+        Ok(addresses) => addresses.collect(),
+        Err(_) => return reply(&mut stream, HOST_UNREACHABLE).await,
+    };
+    let addresses = match allowed_addresses(&host, addresses, deny_private.load(Ordering::Relaxed))
+    {
+        Ok(addresses) => addresses,
+        Err(reason) => {
+            record_denial(
+                &denials,
+                format!("Denied request to {}:{}: {}", host, port, reason),
+            );
+            return reply(&mut stream, NOT_ALLOWED).await;
+        }
+    };
+
+    let mut upstream = match TcpStream::connect(&addresses[..]).await {
+        Ok(upstream) => upstream,
+        Err(_) => return reply(&mut stream, GENERAL_FAILURE).await,
+    };
+    reply(&mut stream, SUCCEEDED).await?;
+    tokio::io::copy_bidirectional(&mut stream, &mut upstream).await?;
+    Ok(())
+}
+
+// The bound address is not reported, clients connecting through the proxy do not need it
+async fn reply(stream: &mut TcpStream, status: u8) -> io::Result<()> {
+    stream
+        .write_all(&[VERSION, status, 0, 1, 0, 0, 0, 0, 0, 0])
+        .await
+}
+
+async fn read_array<const N: usize>(stream: &mut TcpStream) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    stream.read_exact(&mut bytes).await?;
+    Ok(bytes)
+}
+
+// Reads bytes prefixed by their length
+async fn read_bytes(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let [len] = read_array(stream).await?;
+    let mut bytes = vec![0; len as usize];
+    stream.read_exact(&mut bytes).await?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Connects to the proxy as the run owning `proxy` and asks it to connect to `target`,
+    // returning the status of the reply
+    async fn connect(proxy: &Proxy, target: std::net::SocketAddr) -> u8 {
+        let port = *PORT.as_ref().unwrap();
+        let auth = proxy.basic_auth.as_ref().unwrap();
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+
+        stream
+            .write_all(&[VERSION, 1, PASSWORD_AUTH])
+            .await
+            .unwrap();
+        assert_eq!(
+            read_array(&mut stream).await.unwrap(),
+            [VERSION, PASSWORD_AUTH]
+        );
+
+        let mut auth_request = vec![AUTH_VERSION, auth.username.len() as u8];
+        auth_request.extend(auth.username.as_bytes());
+        auth_request.push(auth.password.len() as u8);
+        auth_request.extend(auth.password.as_bytes());
+        stream.write_all(&auth_request).await.unwrap();
+        assert_eq!(read_array(&mut stream).await.unwrap(), [AUTH_VERSION, 0]);
+
+        let std::net::SocketAddr::V4(target) = target else {
+            panic!("IPv4 target expected");
+        };
+        let mut request = vec![VERSION, CONNECT, 0, 1];
+        request.extend(target.ip().octets());
+        request.extend(target.port().to_be_bytes());
+        stream.write_all(&request).await.unwrap();
+        let [_, status, ..] = read_array::<10>(&mut stream).await.unwrap();
+        status
+    }
+
+    #[tokio::test]
+    async fn denies_private_addresses() {
+        let target = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let deny_private = Arc::new(AtomicBool::new(true));
+        let denials = Arc::new(Mutex::new(vec![]));
+        let proxy = register(&deny_private, &denials).unwrap();
+
+        let status = connect(&proxy, target.local_addr().unwrap()).await;
+
+        assert_eq!(status, NOT_ALLOWED);
+        assert_eq!(denials.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn connects_to_allowed_addresses() {
+        let target = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let deny_private = Arc::new(AtomicBool::new(false));
+        let denials = Arc::new(Mutex::new(vec![]));
+        let proxy = register(&deny_private, &denials).unwrap();
+
+        let status = connect(&proxy, target.local_addr().unwrap()).await;
+
+        assert_eq!(status, SUCCEEDED);
+        assert!(denials.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_runs() {
+        let port = *PORT.as_ref().unwrap();
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        stream
+            .write_all(&[VERSION, 1, PASSWORD_AUTH])
+            .await
+            .unwrap();
+        assert_eq!(
+            read_array(&mut stream).await.unwrap(),
+            [VERSION, PASSWORD_AUTH]
+        );
+        stream
+            .write_all(&[AUTH_VERSION, 1, b'a', 1, b'b'])
+            .await
+            .unwrap();
+        assert_eq!(read_array(&mut stream).await.unwrap(), [AUTH_VERSION, 1]);
+    }
+}
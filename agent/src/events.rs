@@ -0,0 +1,63 @@
+use crate::db::models::Log;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+// Events not received by a slow subscriber within this many newer events are dropped for it
+const CAPACITY: usize = 1024;
+
+static EVENTS: LazyLock<broadcast::Sender<Event>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// Something that happened while the agent was running, pushed to the subscribers of the event
+/// stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// Pipeline of the run the event belongs to, if any.
+    pub pipeline_id: Option<Uuid>,
+    /// History of the run the event belongs to, runs that are not recorded have none.
+    pub history_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EventKind {
+    ExecutionStarted,
+    NodeStarted {
+        node_id: String,
+        node_name: String,
+    },
+    /// A node attempt ended, `step` is its entry in the execution trace.
+    NodeFinished {
+        step: Value,
+    },
+    ExecutionFinished,
+    ExecutionFailed {
+        error: String,
+    },
+    Log {
+        log: Log,
+    },
+}
+
+/// Pushes an event to the current subscribers, it is dropped when there are none.
+pub fn publish(pipeline_id: Option<Uuid>, history_id: Option<Uuid>, kind: EventKind) {
+    let _ = EVENTS.send(Event {
+        pipeline_id,
+        history_id,
+        kind,
+    });
+}
+
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENTS.subscribe()
+}
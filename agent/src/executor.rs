@@ -1,23 +1,161 @@
-use crate::blocks::boolean::{bool_false, bool_true};
-use crate::db::flow::{Graph, GraphNode};
+use crate::blocks::value::native_value;
+use crate::blocks::{get_block, AsyncBlockContext, Block, BlockContext};
+use crate::db::flow::{Graph, GraphEdge, GraphNode, JoinMode, NetworkPolicy, MAX_BACKOFF_MS};
 use crate::db::get_node;
+use crate::db::libraries::get_library_by_name;
 use crate::db::models::Node;
+use crate::events::{self, EventKind};
+use crate::kv;
+use crate::kv::{KvStore, KV_OPS_SCRIPT, KV_SCRIPT};
+use crate::modules::{is_module, script_specifier, DbModuleLoader};
+use crate::sandbox;
+use crate::sandbox::{NetworkSandbox, LIMIT_FETCH_SCRIPT};
+use crate::secrets::{referenced_names, Secrets, SECRETS_SCRIPT};
+use crate::typescript::node_source;
+use crate::validation::schema;
+use chrono::{DateTime, Utc};
 use deno_core::_ops::{RustToV8, RustToV8NoScope};
-use deno_core::error::AnyError;
+use deno_core::error::{AnyError, CoreError};
 use deno_core::serde_v8::to_v8;
 use deno_core::v8::{ContextOptions, Function, Global, HandleScope, Local, ObjectTemplate};
-use deno_core::{serde_v8, v8, JsRuntime, RuntimeOptions};
-use std::collections::HashMap;
+use deno_core::{serde_v8, v8, JsRuntime, ModuleLoader, PollEventLoopOptions, RuntimeOptions};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
+// Installed in the context of every script, the environment variables are frozen all the way down
+const ENV_SCRIPT: &str = r#"
+(env) => {
+  const freeze = (value) => {
+    if (value && typeof value === "object") {
+      Object.values(value).forEach(freeze);
+      Object.freeze(value);
+    }
+    return value;
+  };
+  Object.defineProperty(globalThis, "env", { value: freeze(env) });
+}
+"#;
+
+// Installed in the context of every script, lines written through `console` are collected in
+// `sink` until the node finishes
+const CONSOLE_SCRIPT: &str = r#"
+(sink) => {
+  const format = (value) => {
+    if (typeof value === "string") {
+      return value;
+    }
+    try {
+      return JSON.stringify(value) ?? String(value);
+    } catch {
+      return String(value);
+    }
+  };
+  const write = (level) => (...args) => {
+    sink.push({ level, message: args.map(format).join(" ") });
+  };
+  globalThis.console = Object.freeze({
+    log: write("log"),
+    info: write("info"),
+    warn: write("warn"),
+    error: write("error"),
+    debug: write("debug"),
+  });
+}
+"#;
+
+// Source of the inputs of a node run on its own
+const RUN_INPUTS_ID: &str = "inputs";
+
 #[derive(Debug, Clone)]
 pub struct CombinedNode {
     pub graph_node: GraphNode,
     pub db_node: Node,
 }
 
+/// A single node execution recorded while running a graph.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionStep {
+    pub node_id: String,
+    pub node_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub iteration: Vec<Iteration>,
+    pub status: String,
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// A line written by a node script through `console`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsoleEntry {
+    #[serde(default)]
+    pub node_id: String,
+    pub level: String,
+    pub message: String,
+}
+
+/// Identifies one pass of a `ForEach` loop, outermost loop first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Iteration {
+    pub node_id: String,
+    pub index: usize,
+}
+
+/// An exception thrown by a node script, routed to the node's `error` output when connected.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptError {
+    pub message: String,
+    pub stack: Option<String>,
+    pub node_id: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Node {} failed: {}", self.node_id, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Masks secret values in an error, keeping the details of exceptions thrown by scripts.
+pub fn redact_error(secrets: &Secrets, error: AnyError) -> AnyError {
+    match error.downcast::<ScriptError>() {
+        Ok(error) => ScriptError {
+            message: secrets.redact(&error.message),
+            stack: error.stack.map(|stack| secrets.redact(&stack)),
+            node_id: error.node_id,
+        }
+        .into(),
+        Err(error) => AnyError::msg(secrets.redact(&error.to_string())),
+    }
+}
+
+// Per-iteration state of a loop body while it is being executed
+struct IterationState {
+    index: usize,
+    cache: HashMap<String, HashMap<String, Global<v8::Value>>>,
+    halted: HashSet<String>,
+    caught: HashSet<String>,
+    scheduled: HashSet<String>,
+    fired_edges: HashSet<String>,
+    dead_edges: HashSet<String>,
+    queue: Vec<CombinedNode>,
+    reached_collect: bool,
+}
+
 pub struct GraphExecutor {
     nodes: HashMap<String, CombinedNode>,
     graph: Graph,
@@ -26,23 +164,47 @@ pub struct GraphExecutor {
     current_node: CombinedNode,
     entry_node_graph_id: String,
     end_node_graph_id: String,
+    iteration_path: Vec<Iteration>,
+    halted: HashSet<String>,
+    // Nodes whose failure was routed to their `error` output
+    caught: HashSet<String>,
+    scheduled: HashSet<String>,
+    fired_edges: HashSet<String>,
+    dead_edges: HashSet<String>,
+    step_details: Option<serde_json::Value>,
+    secrets: Secrets,
+    kv_ops: Global<v8::Value>,
+    console_sink: Global<v8::Value>,
+    module_handles: HashMap<Uuid, Global<Function>>,
+    module_globals_installed: bool,
 
     pub current_node_queue: Vec<CombinedNode>,
     pub next_node_queue: Vec<CombinedNode>,
     pub reached_end: bool,
+    pub trace: Vec<ExecutionStep>,
+    /// Lines written through `console` by the scripts run so far.
+    pub console: Vec<ConsoleEntry>,
+    /// Checks every value crossing a data edge against the port schemas on both of its ends.
+    pub validate_types: bool,
+    /// Hosts the HTTP node may call.
+    pub allowed_hosts: Vec<String>,
+    /// Variables of the environment, read-only `env` of scripts and part of the context of
+    /// `BeginRequest`.
+    pub env: serde_json::Value,
+    /// Default namespace of the `kv` store of scripts.
+    pub kv_namespace: String,
+    /// Outputs of graph nodes keyed by graph node id, returned by those nodes instead of running
+    /// them.
+    pub mocks: HashMap<String, serde_json::Value>,
+    /// Pipeline and history the events of this run are published for, runs without a history
+    /// publish none.
+    pub pipeline_id: Option<Uuid>,
+    pub history_id: Option<Uuid>,
 }
 
 impl GraphExecutor {
-    pub async fn new(graph: Graph, client: &Client) -> Result<Self, AnyError> {
-        let runtime = JsRuntime::new(RuntimeOptions {
-            module_loader: Some(Rc::new(deno_core::FsModuleLoader)),
-            extensions: vec![
-                deno_fetch::deno_fetch::init_ops_and_esm::<deno_permissions::PermissionsContainer>(deno_fetch::Options::default())
-            ],
-            ..Default::default()
-        });
-        let data_cache = HashMap::new();
-
+    /// Sets up an executor for a graph, errors are returned with secrets redacted.
+    pub async fn new(graph: Graph, client: &Arc<Client>) -> Result<Self, AnyError> {
         // Fetch nodes from the database
         let node_ids = graph
             .nodes
@@ -63,30 +225,104 @@ impl GraphExecutor {
                 }
             }
         }
+
+        let names = referenced_names(&graph, &nodes, |name| async move {
+            Ok(get_library_by_name(client, &name)
+                .await?
+                .map(|library| library.source))
+        })
+        .await?;
+        let secrets = Secrets::load(client, &names).await?;
+        let mut executor =
+            Self::with_nodes(graph, nodes, Some(client)).map_err(|e| redact_error(&secrets, e))?;
+        executor.secrets = secrets;
+        Ok(executor)
+    }
+
+    // Sets up an executor for graph nodes that were already fetched. Without a database client,
+    // scripts can neither import libraries nor use the `kv` store.
+    fn with_nodes(
+        graph: Graph,
+        nodes: Vec<Node>,
+        client: Option<&Arc<Client>>,
+    ) -> Result<Self, AnyError> {
+        let network = NetworkSandbox::new(vec![sandbox::global_policy()]);
+        let mut runtime = JsRuntime::new(RuntimeOptions {
+            module_loader: client
+                .map(|client| Rc::new(DbModuleLoader::new(client.clone())) as Rc<dyn ModuleLoader>),
+            extensions: vec![
+                deno_fetch::deno_fetch::init_ops_and_esm::<NetworkSandbox>(deno_fetch::Options {
+                    proxy: Some(network.egress_proxy()?),
+                    ..Default::default()
+                }),
+                kv::kv::init_ops(),
+            ],
+            ..Default::default()
+        });
+        {
+            let op_state = runtime.op_state();
+            let mut op_state = op_state.borrow_mut();
+            op_state.put(network);
+            if let Some(client) = client {
+                op_state.put(KvStore {
+                    client: client.clone(),
+                });
+            }
+        }
+        let kv_ops = runtime.execute_script("kv_ops", KV_OPS_SCRIPT)?;
+        let console_sink = runtime.execute_script("console_sink", "[]")?;
+        let data_cache = HashMap::new();
         let nodes = get_combined_nodes(graph.clone(), nodes);
 
-        let entry_node = nodes
-            .iter()
-            .map(|node| node.1.clone())
+        // Nodes run on their own have neither a BeginRequest nor an EndRequest
+        let entry_node_graph_id = nodes
+            .values()
             .find(|node| node.db_node.name == "BeginRequest" && node.db_node.is_internal)
-            .unwrap();
-        let end_node = nodes
-            .iter()
-            .map(|node| node.1.clone())
+            .map(|node| node.graph_node.id.clone())
+            .unwrap_or_default();
+        let end_node_graph_id = nodes
+            .values()
             .find(|node| node.db_node.name == "EndRequest" && node.db_node.is_internal)
-            .unwrap();
+            .map(|node| node.graph_node.id.clone())
+            .unwrap_or_default();
+        let current_node = nodes
+            .get(&entry_node_graph_id)
+            .or_else(|| nodes.values().next())
+            .cloned()
+            .ok_or_else(|| AnyError::msg("Graph has no nodes"))?;
 
         Ok(GraphExecutor {
             nodes,
             graph,
             runtime,
             data_cache,
-            current_node: entry_node.clone(),
-            entry_node_graph_id: entry_node.graph_node.id.clone(),
-            end_node_graph_id: end_node.graph_node.id.clone(),
+            current_node,
+            entry_node_graph_id,
+            end_node_graph_id,
+            iteration_path: vec![],
+            halted: HashSet::new(),
+            caught: HashSet::new(),
+            scheduled: HashSet::new(),
+            fired_edges: HashSet::new(),
+            dead_edges: HashSet::new(),
+            step_details: None,
+            secrets: Secrets::default(),
+            kv_ops,
+            console_sink,
+            module_handles: HashMap::new(),
+            module_globals_installed: false,
             current_node_queue: vec![],
             next_node_queue: vec![],
             reached_end: false,
+            trace: vec![],
+            console: vec![],
+            validate_types: false,
+            allowed_hosts: vec![],
+            env: serde_json::json!({}),
+            kv_namespace: "default".to_string(),
+            mocks: HashMap::new(),
+            pipeline_id: None,
+            history_id: None,
         })
     }
 
@@ -96,6 +332,8 @@ impl GraphExecutor {
         let global_obj = to_v8(scope, init_value)?.to_v8();
         let global_obj = Global::new(scope, global_obj);
         init_out.insert("data".to_string(), global_obj);
+        let context = to_v8(scope, serde_json::json!({ "env": self.env }))?;
+        init_out.insert("context".to_string(), Global::new(scope, context));
         self.data_cache
             .insert(self.entry_node_graph_id.clone(), init_out);
 
@@ -118,137 +356,355 @@ impl GraphExecutor {
         self.current_node_queue = nodes;
     }
 
-    pub fn update_next_node_queue(&mut self) {
-        let mut nodes = vec![];
+    pub fn update_next_node_queue(&mut self) -> Result<(), AnyError> {
+        let mut nodes: Vec<CombinedNode> = vec![];
+        let mut fired_edges = vec![];
+        let mut dead_edges = vec![];
         for node in &self.current_node_queue {
-            let edges = self
-                .graph
-                .edges
-                .iter()
-                .filter(|edge| {
-                    edge.source == node.graph_node.id && edge.source_handle == "from-node"
-                })
-                .collect::<Vec<_>>();
+            // A loop continues at its Collect node, the body is driven by the loop itself
+            if node.db_node.is_internal && node.db_node.name == "ForEach" {
+                let (_, collect_id) = self.loop_body(&node.graph_node.id)?;
+                nodes.push(self.nodes[&collect_id].clone());
+                continue;
+            }
 
-            for edge in edges {
-                let target_node = self
-                    .nodes
-                    .get(&edge.target)
-                    .expect(format!("Target node not found for edge: {}", edge.id).as_str());
+            let halted = self.halted.contains(&node.graph_node.id);
+            let caught = self.caught.contains(&node.graph_node.id);
+            for edge in self.control_edges_from(&node.graph_node.id) {
+                // A caught failure only continues through the edges leaving the `error` port
+                let fired = match caught {
+                    true => edge.source_handle == "output-error",
+                    false => !halted && self.edge_fired(edge),
+                };
+                if fired {
+                    fired_edges.push(edge.id.clone());
+                } else {
+                    dead_edges.push(edge.id.clone());
+                }
+            }
+        }
+        self.fired_edges.extend(fired_edges);
+        self.prune_edges(dead_edges);
 
-                nodes.push(target_node.clone());
+        // Schedule every node reached by a fired edge once its join condition is met
+        let mut ready = vec![];
+        for edge in self
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| self.fired_edges.contains(&edge.id))
+        {
+            if !self.scheduled.contains(&edge.target)
+                && !ready.contains(&edge.target)
+                && self.join_ready(&edge.target)
+            {
+                ready.push(edge.target.clone());
             }
         }
-        // Distinct by node graph id
-        // nodes.dedup_by(|a, b| a.graph_node.id == b.graph_node.id);
+        for id in ready {
+            nodes.push(self.nodes[&id].clone());
+            self.scheduled.insert(id);
+        }
 
         self.next_node_queue = nodes;
+        Ok(())
+    }
+
+    // Only runs recorded in history publish their events, dry runs, test runs and debug sessions
+    // would otherwise show up in the event stream as runs of the pipeline
+    fn publish(&self, kind: EventKind) {
+        if self.history_id.is_some() {
+            events::publish(self.pipeline_id, self.history_id, kind);
+        }
     }
 
-    pub fn exec_current_queue(&mut self) -> Result<(), AnyError> {
+    pub async fn exec_current_queue(&mut self) -> Result<(), AnyError> {
         let queue = self.current_node_queue.clone();
         for node in queue {
             self.current_node = node;
-            self.exec_current_node()?;
+            self.publish(EventKind::NodeStarted {
+                node_id: self.current_node.graph_node.id.clone(),
+                node_name: self.current_node.db_node.name.clone(),
+            });
+
+            let mut attempt = 1;
+            let (result, caught) = loop {
+                let started_at = Utc::now();
+                let result = self.exec_current_node().await;
+                self.collect_console();
+                let retry_delay = match &result {
+                    Err(e) => self.retry_delay(attempt, e),
+                    Ok(_) => None,
+                };
+                let caught = match &result {
+                    Err(e) if retry_delay.is_none() => self.catch_error(e),
+                    _ => false,
+                };
+                let status = match (&result, caught) {
+                    (Ok(_), _) => "succeeded",
+                    (Err(_), _) if retry_delay.is_some() => "retrying",
+                    (Err(_), true) => "caught",
+                    (Err(_), false) => "failed",
+                };
+                let step = ExecutionStep {
+                    node_id: self.current_node.graph_node.id.clone(),
+                    node_name: self.current_node.db_node.name.clone(),
+                    iteration: self.iteration_path.clone(),
+                    status: status.to_string(),
+                    attempt,
+                    started_at,
+                    duration_ms: (Utc::now() - started_at).num_milliseconds(),
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                    details: self.step_details.take(),
+                };
+                self.publish(EventKind::NodeFinished {
+                    step: self
+                        .secrets
+                        .redact_value(serde_json::to_value(&step).unwrap_or_default()),
+                });
+                self.trace.push(step);
+
+                match retry_delay {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => break (result, caught),
+                }
+            };
+            if !caught {
+                result?;
+            }
         }
         Ok(())
     }
 
+    // Returns how long to wait before retrying the current node, or `None` when its retry
+    // policy does not allow another attempt for this error
+    fn retry_delay(&self, attempt: u32, error: &AnyError) -> Option<Duration> {
+        let policy = self.current_node.graph_node.data.retry.as_ref()?;
+        if attempt >= policy.max_attempts {
+            return None;
+        }
+
+        let message = error.to_string();
+        if !policy.retry_on.is_empty()
+            && !policy
+                .retry_on
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        {
+            return None;
+        }
+
+        let jitter = match policy.jitter_ms.min(MAX_BACKOFF_MS) {
+            0 => 0,
+            jitter_ms => rand::thread_rng().gen_range(0..=jitter_ms),
+        };
+        Some(Duration::from_millis(policy.backoff(attempt).saturating_add(jitter)))
+    }
+
+    // Routes a failure of the current node to its `error` output when one is connected, only
+    // edges leaving the `error` port continue the run from the node.
+    fn catch_error(&mut self, error: &AnyError) -> bool {
+        let node_id = self.current_node.graph_node.id.clone();
+        let has_error_port = self
+            .graph
+            .edges
+            .iter()
+            .any(|edge| edge.source == node_id && edge.source_handle == "output-error");
+        if !has_error_port {
+            return false;
+        }
+
+        let error = match error.downcast_ref::<ScriptError>() {
+            Some(script_error) => script_error.clone(),
+            None => ScriptError {
+                message: error.to_string(),
+                stack: None,
+                node_id: node_id.clone(),
+            },
+        };
+        let scope = &mut self.runtime.handle_scope();
+        let error = match to_v8(scope, error) {
+            Ok(error) => Global::new(scope, error),
+            Err(_) => return false,
+        };
+
+        let mut out_data = HashMap::new();
+        out_data.insert("error".to_string(), error);
+        self.data_cache.insert(node_id.clone(), out_data);
+        self.caught.insert(node_id);
+        true
+    }
+
     pub fn apply_next_queue(&mut self) {
         self.current_node_queue = self.next_node_queue.clone();
         self.next_node_queue = vec![];
     }
 
-    pub fn exec_current_node(&mut self) -> Result<(), AnyError> {
-        let isolated = self.runtime.v8_isolate();
-        let handle_scope = &mut v8::HandleScope::new(isolated);
-        let context = v8::Context::new(
-            handle_scope,
-            ContextOptions {
-                global_template: None,
-                global_object: None,
-                microtask_queue: None,
-            },
-        );
-        let scope = &mut v8::ContextScope::new(handle_scope, context);
+    pub async fn exec_current_node(&mut self) -> Result<(), AnyError> {
+        if self.current_node.db_node.is_internal && self.current_node.db_node.name == "ForEach" {
+            return self.exec_for_each().await;
+        }
 
         if self.current_node.graph_node.id == self.entry_node_graph_id {
             return Ok(());
         }
 
-        let in_data = self
-            .graph
-            .edges
-            .iter()
-            .filter(|edge| {
-                edge.target == self.current_node.graph_node.id && edge.target_handle != "to-node"
-            })
-            .map(|edge| {
-                let source_data = self
-                    .data_cache
-                    .get(&edge.source)
-                    .expect(format!("Source data not found for edge: {}", edge.id).as_str());
-                let source_handle = edge.source_handle.trim_start_matches("output-").to_string();
-                let data = source_data.get(&source_handle).unwrap().clone();
-
-                let target_handle = edge.target_handle.trim_start_matches("input-").to_string();
-                (target_handle, data)
-            })
-            .collect::<HashMap<_, _>>();
+        if let Some(outputs) = self.mocks.get(&self.current_node.graph_node.id)
+            && self.current_node.graph_node.id != self.end_node_graph_id
+        {
+            return self.store_mocked_outputs(outputs.clone());
+        }
 
-        if self.current_node.db_node.is_internal {
-            return process_internal_nodes(
-                &self.current_node,
-                &mut self.current_node_queue,
-                &mut self.data_cache,
-                &mut self.reached_end,
-                &mut self.end_node_graph_id,
-                in_data,
-                scope,
-            );
+        // Blocks such as Merge coalesce alternative branches, so they never require every input
+        let lenient = self.current_node.graph_node.data.join == JoinMode::AnyAvailable
+            || (self.current_node.db_node.is_internal
+                && get_block(&self.current_node.db_node.name).is_some_and(|block| block.lenient()));
+        let in_data = self.collect_inputs(&self.current_node.graph_node.id, lenient)?;
+        if self.validate_types {
+            self.check_input_types(&self.current_node.graph_node.id.clone())?;
+        }
+
+        let config = self.node_config()?;
+        let network = {
+            let op_state = self.runtime.op_state();
+            let mut op_state = op_state.borrow_mut();
+            let network = op_state.borrow_mut::<NetworkSandbox>();
+            network.set_node_policy(self.current_node.graph_node.data.network.clone());
+            network.clone()
+        };
+        let max_response_bytes = network.max_response_bytes();
+
+        if let Some(block) = get_block(&self.current_node.db_node.name)
+            && self.current_node.db_node.is_internal
+            && block.is_async()
+        {
+            return self.exec_async_block(block, in_data, config, network).await;
+        }
+
+        // TypeScript sources are run once transpiled
+        let source = node_source(&self.current_node.db_node).map_err(|e| ScriptError {
+            message: e.to_string(),
+            stack: None,
+            node_id: self.current_node.graph_node.id.clone(),
+        })?;
+
+        if !self.current_node.db_node.is_internal && is_module(&source) {
+            return self.exec_module_node(in_data, config, source).await;
         }
 
-        // Build the input object for the current node
-        let in_obj_map = {
-            let in_obj_template = ObjectTemplate::new(scope);
-            let in_obj = in_obj_template.new_instance(scope).unwrap();
-            for (key, value) in in_data.iter() {
-                let v8_key = v8::String::new(scope, key).unwrap();
-                let v8_value = value.clone().to_v8(scope);
-                in_obj.set(scope, v8_key.into(), v8_value).unwrap();
+        let kv_ops = self.kv_ops.clone();
+        let console_sink = self.console_sink.clone();
+        let result = {
+            let isolated = self.runtime.v8_isolate();
+            let handle_scope = &mut v8::HandleScope::new(isolated);
+            let context = v8::Context::new(
+                handle_scope,
+                ContextOptions {
+                    global_template: None,
+                    global_object: None,
+                    microtask_queue: None,
+                },
+            );
+            let scope = &mut v8::ContextScope::new(handle_scope, context);
+
+            if self.current_node.db_node.is_internal {
+                return process_internal_nodes(
+                    &self.current_node,
+                    &mut self.halted,
+                    &mut self.data_cache,
+                    &mut self.reached_end,
+                    &mut self.end_node_graph_id,
+                    config,
+                    in_data,
+                    scope,
+                );
             }
 
-            in_obj.to_v8()
-        };
+            // Build the input object for the current node
+            let in_obj_map = input_object(scope, &in_data);
 
-        // Execute the current node's script
-        let current_node = self
-            .nodes
-            .get(&self.current_node.graph_node.id)
-            .expect("Current node not found");
-        let script = current_node.db_node.script.clone();
+            let config = to_v8(scope, config)?;
+
+            let scope = &mut v8::TryCatch::new(scope);
 
-        let fn_name = v8::String::new(scope, "handle").unwrap();
-        let script = v8::String::new(scope, &script).unwrap();
-        let script = v8::Script::compile(scope, script, None).unwrap();
-        script.run(scope).expect("Failed to execute script");
+            // Cap the size of responses read through `fetch` when a policy asks for it
+            if let Some(max_response_bytes) = max_response_bytes {
+                let max = v8::Number::new(scope, max_response_bytes as f64).into();
+                if install_global(scope, LIMIT_FETCH_SCRIPT, &[max]).is_none() {
+                    return Err(script_error(scope, &self.current_node.graph_node.id).into());
+                }
+            }
 
-        let global = context.global(scope);
-        let function_obj = global.get(scope, fn_name.into()).unwrap();
+            // Expose `secrets.get(name)`, the read-only `env`, the `kv` store and `console` to the
+            // script
+            let secrets = to_v8(scope, self.secrets.values())?;
+            let env = to_v8(scope, &self.env)?;
+            let kv_ops = Local::new(scope, kv_ops);
+            let kv_namespace = v8::String::new(scope, &self.kv_namespace).unwrap().into();
+            let console_sink = Local::new(scope, console_sink);
+            if install_global(scope, SECRETS_SCRIPT, &[secrets]).is_none()
+                || install_global(scope, ENV_SCRIPT, &[env]).is_none()
+                || install_global(scope, KV_SCRIPT, &[kv_ops, kv_namespace]).is_none()
+                || install_global(scope, CONSOLE_SCRIPT, &[console_sink]).is_none()
+            {
+                return Err(script_error(scope, &self.current_node.graph_node.id).into());
+            }
 
-        let function: Local<Function> = function_obj.cast();
+            let fn_name = v8::String::new(scope, "handle").unwrap();
+            let script = v8::String::new(scope, &source).unwrap();
+            let function_obj = v8::Script::compile(scope, script, None)
+                .and_then(|script| script.run(scope))
+                .and_then(|_| context.global(scope).get(scope, fn_name.into()));
+            let function_obj = match function_obj {
+                Some(function_obj) if function_obj.is_function() => function_obj,
+                Some(_) => {
+                    return Err(ScriptError {
+                        message: "Node script does not define a handle function".to_string(),
+                        stack: None,
+                        node_id: self.current_node.graph_node.id.clone(),
+                    }
+                    .into());
+                }
+                None => return Err(script_error(scope, &self.current_node.graph_node.id).into()),
+            };
 
-        let result = function.call(scope, function_obj.into(), &[in_obj_map]);
+            let function: Local<Function> = function_obj.cast();
 
-        if result.is_none() {
-            return Err(AnyError::msg(
-                "Function call failed: ".to_owned() + &*self.current_node.graph_node.id,
-            ));
-        }
+            let Some(result) = function.call(scope, function_obj, &[in_obj_map, config]) else {
+                return Err(script_error(scope, &self.current_node.graph_node.id).into());
+            };
+            Global::new(scope, result)
+        };
 
-        let result = result.unwrap().to_object(scope).unwrap();
+        self.store_script_result(result).await
+    }
 
-        let out_data = current_node
+    /// Stores the outputs returned by the `handle` of the current node, waiting for them first
+    /// when it is async.
+    async fn store_script_result(&mut self, result: Global<v8::Value>) -> Result<(), AnyError> {
+        // An async `handle` settles while the event loop drives its pending ops
+        let resolve = self.runtime.resolve(result);
+        let result = self
+            .runtime
+            .with_event_loop_promise(resolve, PollEventLoopOptions::default())
+            .await
+            .map_err(|e| js_script_error(e, &self.current_node.graph_node.id))?;
+
+        let scope = &mut self.runtime.handle_scope();
+        let Some(result) = Local::new(scope, result).to_object(scope) else {
+            return Err(ScriptError {
+                message: "Node script did not return an object".to_string(),
+                stack: None,
+                node_id: self.current_node.graph_node.id.clone(),
+            }
+            .into());
+        };
+
+        let out_data = self
+            .current_node
             .db_node
             .outputs
             .iter()
@@ -266,6 +722,703 @@ impl GraphExecutor {
         Ok(())
     }
 
+    // Stores the mocked outputs of the current node in place of running it, ports missing from
+    // the mock are undefined
+    fn store_mocked_outputs(&mut self, outputs: serde_json::Value) -> Result<(), AnyError> {
+        let scope = &mut self.runtime.handle_scope();
+        let mut out_data = HashMap::new();
+        for port in &self.current_node.db_node.outputs {
+            let value = match outputs.get(port) {
+                Some(value) => to_v8(scope, value)?,
+                None => v8::undefined(scope).into(),
+            };
+            out_data.insert(port.clone(), Global::new(scope, value));
+        }
+
+        self.step_details = Some(serde_json::json!({ "mocked": true }));
+        self.data_cache
+            .insert(self.current_node.graph_node.id.clone(), out_data);
+
+        Ok(())
+    }
+
+    /// Runs a node script written as an ES module. The module is evaluated once per run in the
+    /// main realm, where shared libraries are resolved by the module loader, and its default
+    /// export is called like the `handle` of a classic script.
+    async fn exec_module_node(
+        &mut self,
+        in_data: HashMap<String, Global<v8::Value>>,
+        config: serde_json::Value,
+        source: Arc<str>,
+    ) -> Result<(), AnyError> {
+        self.install_module_globals()?;
+        let handle = self.module_handle(&source).await?;
+
+        let result = {
+            let scope = &mut self.runtime.handle_scope();
+            let input = input_object(scope, &in_data);
+            let config = to_v8(scope, config)?;
+            let scope = &mut v8::TryCatch::new(scope);
+            let handle = Local::new(scope, handle);
+            let receiver = v8::undefined(scope).into();
+            let Some(result) = handle.call(scope, receiver, &[input, config]) else {
+                return Err(script_error(scope, &self.current_node.graph_node.id).into());
+            };
+            Global::new(scope, result)
+        };
+
+        self.store_script_result(result).await
+    }
+
+    // Loads and evaluates the module of the current node unless it already was, returning its
+    // default export
+    async fn module_handle(&mut self, source: &str) -> Result<Global<Function>, AnyError> {
+        let node_id = self.current_node.db_node.id;
+        if let Some(handle) = self.module_handles.get(&node_id) {
+            return Ok(handle.clone());
+        }
+
+        let graph_node_id = self.current_node.graph_node.id.clone();
+        let module_id = self
+            .runtime
+            .load_side_es_module_from_code(&script_specifier(node_id), source.to_string())
+            .await
+            .map_err(|e| js_script_error(e, &graph_node_id))?;
+        let evaluate = Box::pin(self.runtime.mod_evaluate(module_id));
+        self.runtime
+            .with_event_loop_promise(evaluate, PollEventLoopOptions::default())
+            .await
+            .map_err(|e| js_script_error(e, &graph_node_id))?;
+
+        let namespace = self.runtime.get_module_namespace(module_id)?;
+        let scope = &mut self.runtime.handle_scope();
+        let namespace = Local::new(scope, namespace);
+        let default = v8::String::new(scope, "default").unwrap();
+        let handle = namespace
+            .get(scope, default.into())
+            .and_then(|handle| Local::<Function>::try_from(handle).ok())
+            .ok_or_else(|| ScriptError {
+                message: "Node module does not export a default handle function".to_string(),
+                stack: None,
+                node_id: graph_node_id,
+            })?;
+        let handle = Global::new(scope, handle);
+        self.module_handles.insert(node_id, handle.clone());
+
+        Ok(handle)
+    }
+
+    // Modules share the main realm, so `secrets`, `env`, `kv` and `console` are installed there
+    // once
+    fn install_module_globals(&mut self) -> Result<(), AnyError> {
+        if self.module_globals_installed {
+            return Ok(());
+        }
+
+        let kv_ops = self.kv_ops.clone();
+        let console_sink = self.console_sink.clone();
+        let scope = &mut self.runtime.handle_scope();
+        let secrets = to_v8(scope, self.secrets.values())?;
+        let env = to_v8(scope, &self.env)?;
+        let kv_ops = Local::new(scope, kv_ops);
+        let kv_namespace = v8::String::new(scope, &self.kv_namespace).unwrap().into();
+        let console_sink = Local::new(scope, console_sink);
+        let scope = &mut v8::TryCatch::new(scope);
+        if install_global(scope, SECRETS_SCRIPT, &[secrets]).is_none()
+            || install_global(scope, ENV_SCRIPT, &[env]).is_none()
+            || install_global(scope, KV_SCRIPT, &[kv_ops, kv_namespace]).is_none()
+            || install_global(scope, CONSOLE_SCRIPT, &[console_sink]).is_none()
+        {
+            return Err(script_error(scope, &self.current_node.graph_node.id).into());
+        }
+        self.module_globals_installed = true;
+
+        Ok(())
+    }
+
+    /// Runs the body of the current `ForEach` node once per element of its `items` input.
+    ///
+    /// The body is every node reachable from the loop through control edges up to its
+    /// matching `Collect` node. Iterations run sequentially, each one finishes before the next
+    /// starts. The `value` reaching `Collect` in each iteration is gathered into its `results`
+    /// array, iterations that are stopped before reaching it (e.g. by a `Breaker`) are left out.
+    async fn exec_for_each(&mut self) -> Result<(), AnyError> {
+        let for_each_id = self.current_node.graph_node.id.clone();
+        let in_data = self.collect_inputs(&for_each_id, false)?;
+        let (body, collect_id) = self.loop_body(&for_each_id)?;
+
+        let items = {
+            let scope = &mut self.runtime.handle_scope();
+            let items = in_data
+                .get("items")
+                .ok_or_else(|| AnyError::msg("ForEach requires an items input"))?
+                .clone()
+                .to_v8(scope);
+            let items: Local<v8::Array> = items
+                .try_into()
+                .map_err(|_| AnyError::msg("ForEach items must be an array"))?;
+            (0..items.length())
+                .map(|i| {
+                    let item = items.get_index(scope, i).unwrap();
+                    Global::new(scope, item)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let entry_queue = self
+            .control_edges_from(&for_each_id)
+            .filter(|edge| body.contains(&edge.target))
+            .map(|edge| self.nodes[&edge.target].clone())
+            .collect::<Vec<_>>();
+
+        let outer_queue = std::mem::take(&mut self.current_node_queue);
+        let mut results = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let mut state = IterationState {
+                index,
+                cache: HashMap::new(),
+                halted: HashSet::new(),
+                caught: HashSet::new(),
+                scheduled: HashSet::new(),
+                fired_edges: HashSet::new(),
+                dead_edges: HashSet::new(),
+                queue: entry_queue.clone(),
+                reached_collect: false,
+            };
+
+            while !state.queue.is_empty() {
+                let value = self
+                    .exec_iteration_step(&for_each_id, &collect_id, &body, item, &mut state)
+                    .await;
+                match value {
+                    Ok(Some(value)) => results.push(value),
+                    Ok(None) => {}
+                    Err(e) => {
+                        self.current_node_queue = outer_queue;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        self.current_node_queue = outer_queue;
+
+        let scope = &mut self.runtime.handle_scope();
+        let elements = results
+            .into_iter()
+            .map(|value| value.to_v8(scope))
+            .collect::<Vec<_>>();
+        let array = v8::Array::new_with_elements(scope, &elements).to_v8();
+
+        let mut out_data = HashMap::new();
+        out_data.insert("results".to_string(), Global::new(scope, array));
+        self.data_cache.remove(&for_each_id);
+        self.data_cache.insert(collect_id, out_data);
+
+        Ok(())
+    }
+
+    // Advances a single iteration of a loop body by one queue, returning the collected value
+    // once the iteration has finished.
+    async fn exec_iteration_step(
+        &mut self,
+        for_each_id: &str,
+        collect_id: &str,
+        body: &HashSet<String>,
+        item: &Global<v8::Value>,
+        state: &mut IterationState,
+    ) -> Result<Option<Global<v8::Value>>, AnyError> {
+        {
+            let scope = &mut self.runtime.handle_scope();
+            let index = v8::Number::new(scope, state.index as f64).to_v8();
+            let mut loop_out = HashMap::new();
+            loop_out.insert("item".to_string(), item.clone());
+            loop_out.insert("index".to_string(), Global::new(scope, index));
+            self.data_cache.insert(for_each_id.to_string(), loop_out);
+        }
+        self.data_cache.extend(state.cache.drain());
+        self.halted.extend(state.halted.drain());
+        self.caught.extend(state.caught.drain());
+        self.scheduled.extend(state.scheduled.drain());
+        self.fired_edges.extend(state.fired_edges.drain());
+        self.dead_edges.extend(state.dead_edges.drain());
+        self.current_node_queue = std::mem::take(&mut state.queue);
+        self.iteration_path.push(Iteration {
+            node_id: for_each_id.to_string(),
+            index: state.index,
+        });
+
+        let result = Box::pin(self.exec_current_queue()).await;
+        self.iteration_path.pop();
+        result?;
+
+        self.update_next_node_queue()?;
+        for node in std::mem::take(&mut self.next_node_queue) {
+            if node.graph_node.id == collect_id {
+                state.reached_collect = true;
+            } else if body.contains(&node.graph_node.id) {
+                state.queue.push(node);
+            }
+        }
+
+        let mut value = None;
+        if state.queue.is_empty() && state.reached_collect {
+            value = self.collect_inputs(collect_id, false)?.remove("value");
+        }
+        for id in body {
+            if let Some(out_data) = self.data_cache.remove(id) {
+                state.cache.insert(id.clone(), out_data);
+            }
+            if self.halted.remove(id) {
+                state.halted.insert(id.clone());
+            }
+            if self.caught.remove(id) {
+                state.caught.insert(id.clone());
+            }
+            if self.scheduled.remove(id) {
+                state.scheduled.insert(id.clone());
+            }
+        }
+        for edge in &self.graph.edges {
+            if !body.contains(&edge.source) && !body.contains(&edge.target) {
+                continue;
+            }
+            if self.fired_edges.remove(&edge.id) {
+                state.fired_edges.insert(edge.id.clone());
+            }
+            if self.dead_edges.remove(&edge.id) {
+                state.dead_edges.insert(edge.id.clone());
+            }
+        }
+
+        Ok(value)
+    }
+
+    // Finds the nodes making up the body of a `ForEach` loop and its matching `Collect` node
+    fn loop_body(&self, for_each_id: &str) -> Result<(HashSet<String>, String), AnyError> {
+        let mut body = HashSet::new();
+        let mut collect_id = None;
+        let mut visited = HashSet::new();
+        let mut stack = vec![(for_each_id.to_string(), 0usize)];
+
+        while let Some((id, depth)) = stack.pop() {
+            for edge in self.control_edges_from(&id) {
+                let target = &self.nodes[&edge.target];
+                let mut depth = depth;
+                if target.db_node.is_internal && target.db_node.name == "Collect" {
+                    if depth == 0 {
+                        if collect_id.as_ref().is_some_and(|c| *c != edge.target) {
+                            return Err(AnyError::msg(format!(
+                                "ForEach {} reaches more than one Collect node",
+                                for_each_id
+                            )));
+                        }
+                        collect_id = Some(edge.target.clone());
+                        continue;
+                    }
+                    depth -= 1;
+                } else if target.db_node.is_internal && target.db_node.name == "ForEach" {
+                    depth += 1;
+                }
+
+                if visited.insert((edge.target.clone(), depth)) {
+                    body.insert(edge.target.clone());
+                    stack.push((edge.target.clone(), depth));
+                }
+            }
+        }
+
+        let collect_id = collect_id
+            .ok_or_else(|| AnyError::msg(format!("ForEach {} has no Collect node", for_each_id)))?;
+
+        if let Some(edge) = self.graph.edges.iter().find(|edge| {
+            edge.target_handle == "to-node"
+                && body.contains(&edge.target)
+                && edge.source != for_each_id
+                && !body.contains(&edge.source)
+        }) {
+            return Err(AnyError::msg(format!(
+                "Node {} inside the body of ForEach {} is also reachable from outside the loop",
+                edge.target, for_each_id
+            )));
+        }
+
+        Ok((body, collect_id))
+    }
+
+    // Marks control edges that will never fire, skipping every node whose incoming control
+    // edges are all dead so untaken branches are pruned transitively
+    fn prune_edges(&mut self, edges: Vec<String>) {
+        let mut pending = edges;
+        while let Some(edge_id) = pending.pop() {
+            if !self.dead_edges.insert(edge_id.clone()) {
+                continue;
+            }
+
+            let edge = self.graph.edges.iter().find(|e| e.id == edge_id).unwrap();
+            let target = &self.nodes[&edge.target];
+            // Collect nodes are only ever reached through their ForEach
+            if target.db_node.is_internal && target.db_node.name == "Collect" {
+                continue;
+            }
+
+            let all_dead = self
+                .control_edges_to(&target.graph_node.id)
+                .all(|e| self.dead_edges.contains(&e.id));
+            if all_dead {
+                self.trace.push(ExecutionStep {
+                    node_id: target.graph_node.id.clone(),
+                    node_name: target.db_node.name.clone(),
+                    iteration: self.iteration_path.clone(),
+                    status: "skipped".to_string(),
+                    attempt: 0,
+                    started_at: Utc::now(),
+                    duration_ms: 0,
+                    error: None,
+                    details: None,
+                });
+                pending.extend(
+                    self.control_edges_from(&target.graph_node.id)
+                        .map(|e| e.id.clone()),
+                );
+            }
+        }
+    }
+
+    // Whether a node reached by a fired edge may run according to its join mode
+    fn join_ready(&self, graph_node_id: &str) -> bool {
+        let incoming = self.control_edges_to(graph_node_id).collect::<Vec<_>>();
+        let arrived = incoming
+            .iter()
+            .any(|edge| self.fired_edges.contains(&edge.id));
+
+        match self.nodes[graph_node_id].graph_node.data.join {
+            JoinMode::FirstArrival => arrived,
+            JoinMode::WaitAll | JoinMode::AnyAvailable => {
+                arrived
+                    && incoming.iter().all(|edge| {
+                        self.fired_edges.contains(&edge.id) || self.dead_edges.contains(&edge.id)
+                    })
+            }
+        }
+    }
+
+    // Whether a control edge fires once its source node has run. Edges leaving an output
+    // port only fire when the node produced that output, which is how `Switch` selects a branch.
+    // Edges leaving the `error` port only fire when a failure of the node is caught.
+    fn edge_fired(&self, edge: &GraphEdge) -> bool {
+        match edge.source_handle.strip_prefix("output-") {
+            Some("error") => false,
+            Some(handle) => self
+                .data_cache
+                .get(&edge.source)
+                .is_some_and(|out_data| out_data.contains_key(handle)),
+            None => true,
+        }
+    }
+
+    fn control_edges_from<'a>(
+        &'a self,
+        graph_node_id: &'a str,
+    ) -> impl Iterator<Item = &'a GraphEdge> {
+        self.graph
+            .edges
+            .iter()
+            .filter(move |edge| edge.source == graph_node_id && edge.target_handle == "to-node")
+    }
+
+    fn control_edges_to<'a>(
+        &'a self,
+        graph_node_id: &'a str,
+    ) -> impl Iterator<Item = &'a GraphEdge> {
+        self.graph
+            .edges
+            .iter()
+            .filter(move |edge| edge.target == graph_node_id && edge.target_handle == "to-node")
+    }
+
+    // Resolves the values connected to the data inputs of a graph node. When lenient, inputs
+    // whose source has not produced a value are left out and read as undefined by scripts.
+    fn collect_inputs(
+        &self,
+        graph_node_id: &str,
+        lenient: bool,
+    ) -> Result<HashMap<String, Global<v8::Value>>, AnyError> {
+        let mut in_data = HashMap::new();
+        for edge in self
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| edge.target == graph_node_id && edge.target_handle != "to-node")
+        {
+            let source_handle = edge.source_handle.trim_start_matches("output-");
+            let data = self
+                .data_cache
+                .get(&edge.source)
+                .and_then(|source_data| source_data.get(source_handle));
+
+            let target_handle = edge.target_handle.trim_start_matches("input-").to_string();
+            match data {
+                Some(data) => {
+                    in_data.insert(target_handle, data.clone());
+                }
+                None if lenient => {}
+                None => {
+                    return Err(AnyError::msg(format!(
+                        "Source data not found for edge: {}",
+                        edge.id
+                    )))
+                }
+            }
+        }
+        Ok(in_data)
+    }
+
+    // Static configuration of the current node, completed with the schema defaults and with
+    // secret references resolved
+    fn node_config(&self) -> Result<serde_json::Value, AnyError> {
+        let config = schema::apply_defaults(
+            self.current_node.db_node.config_schema.as_ref(),
+            self.current_node.graph_node.data.config.as_ref(),
+        );
+        self.secrets.resolve(config)
+    }
+
+    pub fn secrets(&self) -> &Secrets {
+        &self.secrets
+    }
+
+    /// The execution trace with secret values masked.
+    pub fn trace_json(&self) -> serde_json::Value {
+        self.secrets
+            .redact_value(serde_json::to_value(&self.trace).unwrap_or_default())
+    }
+
+    /// Adds a policy every request of this run has to satisfy, on top of the global one.
+    pub fn add_network_policy(&mut self, policy: NetworkPolicy) {
+        self.runtime
+            .op_state()
+            .borrow_mut()
+            .borrow_mut::<NetworkSandbox>()
+            .add_policy(policy);
+    }
+
+    /// Removes and returns the network requests denied since the last call.
+    pub fn take_network_denials(&mut self) -> Vec<String> {
+        self.runtime
+            .op_state()
+            .borrow()
+            .borrow::<NetworkSandbox>()
+            .take_denials()
+    }
+
+    // Runs a block that awaits I/O, its values cross the V8 boundary as JSON on both sides
+    async fn exec_async_block(
+        &mut self,
+        block: &'static dyn Block,
+        in_data: HashMap<String, Global<v8::Value>>,
+        config: serde_json::Value,
+        network: NetworkSandbox,
+    ) -> Result<(), AnyError> {
+        let inputs = {
+            let scope = &mut self.runtime.handle_scope();
+            let mut inputs = HashMap::new();
+            for (name, value) in in_data {
+                let value = Local::new(scope, value);
+                inputs.insert(name, serde_v8::from_v8(scope, value)?);
+            }
+            inputs
+        };
+        let ctx = AsyncBlockContext {
+            config,
+            inputs,
+            allowed_hosts: self.allowed_hosts.clone(),
+            network,
+        };
+
+        let output = block.execute_async(ctx).await;
+        self.step_details = output.details;
+        let outputs = output.outputs?;
+
+        let scope = &mut self.runtime.handle_scope();
+        let mut out_data = HashMap::new();
+        for (name, value) in outputs {
+            out_data.insert(name, native_value(scope, &value)?);
+        }
+        self.data_cache
+            .insert(self.current_node.graph_node.id.clone(), out_data);
+
+        Ok(())
+    }
+
+    // Validates the values arriving at a node against the schemas of the ports they connect
+    fn check_input_types(&mut self, graph_node_id: &str) -> Result<(), AnyError> {
+        let scope = &mut self.runtime.handle_scope();
+        for edge in self
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| edge.target == graph_node_id && edge.target_handle != "to-node")
+        {
+            let output = edge.source_handle.trim_start_matches("output-");
+            let input = edge.target_handle.trim_start_matches("input-");
+            let Some(value) = self
+                .data_cache
+                .get(&edge.source)
+                .and_then(|source_data| source_data.get(output))
+            else {
+                continue;
+            };
+
+            let source = &self.nodes[&edge.source].db_node;
+            let target = &self.nodes[&edge.target].db_node;
+            let schemas = [
+                schema::port_schema(source.output_schemas.as_ref(), output),
+                schema::port_schema(target.input_schemas.as_ref(), input),
+            ];
+            if schemas.iter().all(Option::is_none) {
+                continue;
+            }
+
+            let value = Local::new(scope, value);
+            let value: serde_json::Value = serde_v8::from_v8(scope, value)?;
+            let path = format!("{}.{}", source.name, output);
+            let violations: Vec<String> = schemas
+                .iter()
+                .flatten()
+                .flat_map(|port_schema| schema::validate(port_schema, &value, &path))
+                .collect();
+            if !violations.is_empty() {
+                return Err(AnyError::msg(format!(
+                    "Invalid value on edge {} ({}.{} -> {}.{}): {}",
+                    edge.id,
+                    source.name,
+                    output,
+                    target.name,
+                    input,
+                    violations.join("; ")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Moves the lines written through `console` by the current node out of the sink
+    fn collect_console(&mut self) {
+        let scope = &mut self.runtime.handle_scope();
+        let sink = Local::new(scope, &self.console_sink);
+        let Ok(entries) = serde_v8::from_v8::<Vec<ConsoleEntry>>(scope, sink) else {
+            return;
+        };
+        if let Ok(sink) = Local::<v8::Object>::try_from(sink) {
+            let length = v8::String::new(scope, "length").unwrap().into();
+            let zero = v8::Integer::new(scope, 0).into();
+            sink.set(scope, length, zero);
+        }
+
+        let node_id = &self.current_node.graph_node.id;
+        self.console
+            .extend(entries.into_iter().map(|entry| ConsoleEntry {
+                node_id: node_id.clone(),
+                ..entry
+            }));
+    }
+
+    /// Runs a single node of the graph on its own. `inputs` maps input ports of the node to
+    /// their values, which reach it through edges like in a pipeline, ports left out are
+    /// undefined. Returns the outputs of the node.
+    pub async fn run_node(
+        &mut self,
+        graph_node_id: &str,
+        inputs: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<serde_json::Value, AnyError> {
+        let node =
+            self.nodes.get(graph_node_id).cloned().ok_or_else(|| {
+                AnyError::msg(format!("Node {} is not in the graph", graph_node_id))
+            })?;
+
+        let mut values = HashMap::new();
+        {
+            let scope = &mut self.runtime.handle_scope();
+            for (port, value) in inputs {
+                if !node.db_node.inputs.contains(&port) {
+                    continue;
+                }
+                let value = to_v8(scope, value)?;
+                values.insert(port, Global::new(scope, value));
+            }
+        }
+        for port in values.keys() {
+            self.graph.edges.push(GraphEdge {
+                source: RUN_INPUTS_ID.to_string(),
+                source_handle: format!("output-{}", port),
+                target: graph_node_id.to_string(),
+                target_handle: format!("input-{}", port),
+                id: format!("{}-{}", RUN_INPUTS_ID, port),
+            });
+        }
+        self.data_cache.insert(RUN_INPUTS_ID.to_string(), values);
+
+        self.current_node_queue = vec![node];
+        self.exec_current_queue().await?;
+
+        let scope = &mut self.runtime.handle_scope();
+        let mut outputs = serde_json::Map::new();
+        if let Some(out_data) = self.data_cache.get(graph_node_id) {
+            for (port, value) in out_data {
+                let value = Local::new(scope, value);
+                outputs.insert(
+                    port.clone(),
+                    serde_v8::from_v8::<serde_json::Value>(scope, value)?,
+                );
+            }
+        }
+        Ok(serde_json::Value::Object(outputs))
+    }
+
+    /// Values in the data cache keyed by graph node id and port, secrets redacted.
+    pub fn data_json(&mut self) -> serde_json::Value {
+        let scope = &mut self.runtime.handle_scope();
+        let mut data = serde_json::Map::new();
+        for (graph_node_id, values) in &self.data_cache {
+            let values = values
+                .iter()
+                .map(|(port, value)| {
+                    let value = Local::new(scope, value);
+                    let value = serde_v8::from_v8::<serde_json::Value>(scope, value)
+                        .unwrap_or(serde_json::Value::Null);
+                    (port.clone(), value)
+                })
+                .collect();
+            data.insert(graph_node_id.clone(), serde_json::Value::Object(values));
+        }
+        self.secrets.redact_value(serde_json::Value::Object(data))
+    }
+
+    /// Replaces the value a graph node holds on a port in the data cache, nodes that have not run
+    /// yet read it as if the node had produced it.
+    pub fn set_data(
+        &mut self,
+        graph_node_id: &str,
+        port: &str,
+        value: serde_json::Value,
+    ) -> Result<(), AnyError> {
+        if !self.nodes.contains_key(graph_node_id) {
+            return Err(AnyError::msg(format!(
+                "Node {} is not in the graph",
+                graph_node_id
+            )));
+        }
+
+        let scope = &mut self.runtime.handle_scope();
+        let value = to_v8(scope, value)?;
+        self.data_cache
+            .entry(graph_node_id.to_string())
+            .or_default()
+            .insert(port.to_string(), Global::new(scope, value));
+        Ok(())
+    }
+
     pub fn get_result(&mut self) -> Result<serde_json::Value, AnyError> {
         let scope = &mut self.runtime.handle_scope();
 
@@ -285,53 +1438,99 @@ impl GraphExecutor {
     }
 }
 
+// Builds the input object of a node script from its input values
+fn input_object<'s>(
+    scope: &mut HandleScope<'s>,
+    in_data: &HashMap<String, Global<v8::Value>>,
+) -> Local<'s, v8::Value> {
+    let in_obj = ObjectTemplate::new(scope).new_instance(scope).unwrap();
+    for (key, value) in in_data.iter() {
+        let v8_key = v8::String::new(scope, key).unwrap();
+        let v8_value = Local::new(scope, value);
+        in_obj.set(scope, v8_key.into(), v8_value).unwrap();
+    }
+
+    in_obj.into()
+}
+
+// Runs a script evaluating to a function and calls it with `args`, used to set up the globals of
+// a node script
+fn install_global<'s>(
+    scope: &mut HandleScope<'s>,
+    source: &str,
+    args: &[Local<'s, v8::Value>],
+) -> Option<Local<'s, v8::Value>> {
+    let source = v8::String::new(scope, source)?;
+    let install = v8::Script::compile(scope, source, None)?.run(scope)?;
+    let install: Local<Function> = install.cast();
+    let receiver = v8::undefined(scope).into();
+    install.call(scope, receiver, args)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn process_internal_nodes(
     current_node: &CombinedNode,
-    current_node_queue: &mut Vec<CombinedNode>,
+    halted: &mut HashSet<String>,
     data_cache: &mut HashMap<String, HashMap<String, Global<v8::Value>>>,
     reached_end: &mut bool,
     end_node_graph_id: &mut String,
+    config: serde_json::Value,
     in_data: HashMap<String, Global<v8::Value>>,
     scope: &mut HandleScope,
 ) -> Result<(), AnyError> {
-    if current_node.db_node.name == "Breaker" {
-        let condition = in_data.get("condition").unwrap().clone();
-        let condition = condition.to_v8(scope);
-        let condition = serde_v8::from_v8::<bool>(scope, condition)?;
-
-        if !condition {
-            *current_node_queue = current_node_queue
-                .iter()
-                .filter(|node| node.graph_node.id != current_node.graph_node.id)
-                .cloned()
-                .collect();
-        }
-    } else if current_node.db_node.name == "True" {
-        let mut out_data = HashMap::new();
-        out_data.insert("out".to_string(), bool_true(scope));
-        data_cache.insert(current_node.graph_node.id.clone(), out_data);
-    } else if current_node.db_node.name == "False" {
-        let mut out_data = HashMap::new();
-        out_data.insert("out".to_string(), bool_false(scope));
-        data_cache.insert(current_node.graph_node.id.clone(), out_data);
-    } else if current_node.db_node.name == "Empty" {
-        let obj = v8::undefined(scope).to_v8();
-        let obj = Global::new(scope, obj);
-
-        let mut out_data = HashMap::new();
-        out_data.insert("out".to_string(), obj);
-        data_cache.insert(current_node.graph_node.id.clone(), out_data);
+    if current_node.db_node.name == "Collect" {
+        // Results were already stored by the ForEach node owning this Collect
     } else if current_node.db_node.name == "EndRequest" {
         *reached_end = true;
         *end_node_graph_id = current_node.graph_node.id.clone();
         data_cache.insert(current_node.graph_node.id.clone(), in_data);
     } else {
-        return Err(AnyError::msg("Internal node not found"));
+        let block = get_block(&current_node.db_node.name)
+            .ok_or_else(|| AnyError::msg("Internal node not found"))?;
+        let mut ctx = BlockContext::new(scope, current_node, config, in_data);
+        block.execute(&mut ctx)?;
+
+        let (out_data, block_halted) = ctx.finish();
+        if block_halted {
+            halted.insert(current_node.graph_node.id.clone());
+        }
+        data_cache.insert(current_node.graph_node.id.clone(), out_data);
     }
 
     Ok(())
 }
 
+fn script_error(scope: &mut v8::TryCatch<v8::HandleScope>, node_id: &str) -> ScriptError {
+    let message = match scope.exception() {
+        Some(exception) => exception.to_rust_string_lossy(scope),
+        None => "Function call failed".to_string(),
+    };
+    let stack = scope
+        .stack_trace()
+        .map(|stack| stack.to_rust_string_lossy(scope));
+
+    ScriptError {
+        message,
+        stack,
+        node_id: node_id.to_string(),
+    }
+}
+
+// An exception raised while evaluating a module or settling a `handle` promise, reported like one
+// thrown by a classic script
+fn js_script_error(error: CoreError, node_id: &str) -> ScriptError {
+    let (message, stack) = match error {
+        CoreError::Js(js_error) => (js_error.exception_message, js_error.stack),
+        other => (other.to_string(), None),
+    };
+
+    ScriptError {
+        message,
+        stack,
+        node_id: node_id.to_string(),
+    }
+}
+
 fn get_combined_nodes(graph: Graph, db_nodes: Vec<Node>) -> HashMap<String, CombinedNode> {
     let mut combined_nodes = HashMap::new();
 
@@ -395,3 +1594,191 @@ pub fn global_object_to_json(
 
     Ok(json_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::exec::run_graph;
+    use serde_json::json;
+
+    fn node(
+        name: &str,
+        is_internal: bool,
+        script: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+    ) -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            is_internal,
+            name: name.to_string(),
+            script: script.to_string(),
+            inputs: inputs.iter().map(|port| port.to_string()).collect(),
+            outputs: outputs.iter().map(|port| port.to_string()).collect(),
+            config_schema: None,
+            input_schemas: None,
+            output_schemas: None,
+            language: "javascript".to_string(),
+        }
+    }
+
+    fn graph_node(id: &str, node: &Node, join: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "type": "custom",
+            "position": { "x": 0.0, "y": 0.0 },
+            "data": {
+                "id": node.id.to_string(),
+                "name": node.name,
+                "inputs": node.inputs,
+                "outputs": node.outputs,
+                "join": join,
+            },
+            "measured": { "width": 0.0, "height": 0.0 },
+            "selected": false,
+            "dragging": false,
+        })
+    }
+
+    fn edge(
+        source: &str,
+        source_handle: &str,
+        target: &str,
+        target_handle: &str,
+    ) -> serde_json::Value {
+        json!({
+            "id": format!("{}-{}-{}-{}", source, source_handle, target, target_handle),
+            "source": source,
+            "sourceHandle": source_handle,
+            "target": target,
+            "targetHandle": target_handle,
+        })
+    }
+
+    // BeginRequest -> risky, which continues to `normal` and routes its failures to `fallback`.
+    // Both branches end in EndRequest, which takes whichever of them ran.
+    async fn run_risky(script: &str) -> (Result<serde_json::Value, AnyError>, Vec<ExecutionStep>) {
+        let begin = node("BeginRequest", true, "internal", &[], &["data", "context"]);
+        let end = node("EndRequest", true, "internal", &["data", "context"], &[]);
+        let risky = node("Risky", false, script, &["data"], &["value", "error"]);
+        let normal = node(
+            "Normal",
+            false,
+            "function handle(input) { return { value: { ok: input.value } }; }",
+            &["value"],
+            &["value"],
+        );
+        let fallback = node(
+            "Fallback",
+            false,
+            "function handle(input) { return { value: { recovered: input.error.message } }; }",
+            &["error"],
+            &["value"],
+        );
+
+        let graph: Graph = serde_json::from_value(json!({
+            "nodes": [
+                graph_node("begin", &begin, "wait-all"),
+                graph_node("risky", &risky, "wait-all"),
+                graph_node("normal", &normal, "wait-all"),
+                graph_node("fallback", &fallback, "wait-all"),
+                graph_node("end", &end, "any-available"),
+            ],
+            "edges": [
+                edge("begin", "from-node", "risky", "to-node"),
+                edge("begin", "output-data", "risky", "input-data"),
+                edge("risky", "from-node", "normal", "to-node"),
+                edge("risky", "output-value", "normal", "input-value"),
+                edge("risky", "output-error", "fallback", "to-node"),
+                edge("risky", "output-error", "fallback", "input-error"),
+                edge("normal", "from-node", "end", "to-node"),
+                edge("normal", "output-value", "end", "input-data"),
+                edge("fallback", "from-node", "end", "to-node"),
+                edge("fallback", "output-value", "end", "input-data"),
+            ],
+        }))
+        .unwrap();
+
+        let nodes = vec![begin, end, risky, normal, fallback];
+        let mut executor = GraphExecutor::with_nodes(graph, nodes, None).unwrap();
+        let result = run_graph(&mut executor, json!({})).await;
+        (result, executor.trace)
+    }
+
+    fn status<'a>(trace: &'a [ExecutionStep], node_id: &str) -> Option<&'a str> {
+        trace
+            .iter()
+            .rev()
+            .find(|step| step.node_id == node_id)
+            .map(|step| step.status.as_str())
+    }
+
+    #[tokio::test]
+    async fn failure_is_routed_to_the_error_port() {
+        let (result, trace) = run_risky("function handle() { throw new Error(\"boom\"); }").await;
+
+        assert_eq!(result.unwrap(), json!({ "recovered": "Error: boom" }));
+        assert_eq!(status(&trace, "risky"), Some("caught"));
+        assert_eq!(status(&trace, "fallback"), Some("succeeded"));
+        assert_eq!(status(&trace, "normal"), Some("skipped"));
+    }
+
+    #[tokio::test]
+    async fn success_does_not_take_the_error_port() {
+        let (result, trace) = run_risky("function handle() { return { value: 1 }; }").await;
+
+        assert_eq!(result.unwrap(), json!({ "ok": 1 }));
+        assert_eq!(status(&trace, "risky"), Some("succeeded"));
+        assert_eq!(status(&trace, "normal"), Some("succeeded"));
+        assert_eq!(status(&trace, "fallback"), Some("skipped"));
+    }
+
+    #[tokio::test]
+    async fn for_each_collects_the_results_of_every_iteration_in_order() {
+        let begin = node("BeginRequest", true, "internal", &[], &["data", "context"]);
+        let end = node("EndRequest", true, "internal", &["data", "context"], &[]);
+        let for_each = node("ForEach", true, "internal", &["items"], &["item", "index"]);
+        let collect = node("Collect", true, "internal", &["value"], &["results"]);
+        let double = node(
+            "Double",
+            false,
+            "function handle(input) { return { value: input.item * 2 }; }",
+            &["item"],
+            &["value"],
+        );
+
+        let graph: Graph = serde_json::from_value(json!({
+            "nodes": [
+                graph_node("begin", &begin, "wait-all"),
+                graph_node("loop", &for_each, "wait-all"),
+                graph_node("double", &double, "wait-all"),
+                graph_node("collect", &collect, "wait-all"),
+                graph_node("end", &end, "wait-all"),
+            ],
+            "edges": [
+                edge("begin", "from-node", "loop", "to-node"),
+                edge("begin", "output-data", "loop", "input-items"),
+                edge("loop", "from-node", "double", "to-node"),
+                edge("loop", "output-item", "double", "input-item"),
+                edge("double", "from-node", "collect", "to-node"),
+                edge("double", "output-value", "collect", "input-value"),
+                edge("collect", "from-node", "end", "to-node"),
+                edge("collect", "output-results", "end", "input-data"),
+            ],
+        }))
+        .unwrap();
+
+        let nodes = vec![begin, end, for_each, collect, double];
+        let mut executor = GraphExecutor::with_nodes(graph, nodes, None).unwrap();
+        let result = run_graph(&mut executor, json!([1, 2, 3])).await;
+
+        assert_eq!(result.unwrap(), json!([2, 4, 6]));
+        let iterations = executor
+            .trace
+            .iter()
+            .filter(|step| step.node_id == "double" && step.status == "succeeded")
+            .map(|step| step.iteration.last().map(|iteration| iteration.index))
+            .collect::<Vec<_>>();
+        assert_eq!(iterations, vec![Some(0), Some(1), Some(2)]);
+    }
+}
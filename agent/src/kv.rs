@@ -0,0 +1,103 @@
+use crate::db::kv::{kv_cas, kv_delete, kv_get, kv_set};
+use deno_core::{op2, OpState};
+use deno_error::JsErrorBox;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use tokio_postgres::Client;
+
+// Installed in the context of every script with the ops below and the namespace of the
+// pipeline, `kv.namespace(name)` opens another namespace
+pub const KV_SCRIPT: &str = r#"
+(ops, namespace) => {
+  const open = (namespace) => Object.freeze({
+    get: (key) => ops.get(namespace, key),
+    set: (key, value, options) => ops.set(namespace, key, value ?? null, options?.ttl ?? null),
+    delete: (key) => ops.delete(namespace, key),
+    cas: (key, expected, value, options) =>
+      ops.cas(namespace, key, expected ?? null, value ?? null, options?.ttl ?? null),
+    namespace: open,
+  });
+  Object.defineProperty(globalThis, "kv", { value: open(namespace) });
+}
+"#;
+
+// Evaluated in the main realm, where the ops are registered
+pub const KV_OPS_SCRIPT: &str = r#"
+({
+  get: Deno.core.ops.op_kv_get,
+  set: Deno.core.ops.op_kv_set,
+  delete: Deno.core.ops.op_kv_delete,
+  cas: Deno.core.ops.op_kv_cas,
+})
+"#;
+
+/// Database connection used by the key-value ops, stored in the op state of the runtime.
+pub struct KvStore {
+    pub client: Arc<Client>,
+}
+
+deno_core::extension!(kv, ops = [op_kv_get, op_kv_set, op_kv_delete, op_kv_cas]);
+
+fn client(state: &Rc<RefCell<OpState>>) -> Result<Arc<Client>, JsErrorBox> {
+    match state.borrow().try_borrow::<KvStore>() {
+        Some(store) => Ok(store.client.clone()),
+        None => Err(JsErrorBox::generic("Key-value store is not available")),
+    }
+}
+
+fn to_js_error(error: anyhow::Error) -> JsErrorBox {
+    JsErrorBox::generic(format!("Key-value store error: {}", error))
+}
+
+#[op2(async)]
+#[serde]
+async fn op_kv_get(
+    state: Rc<RefCell<OpState>>,
+    #[string] namespace: String,
+    #[string] key: String,
+) -> Result<Option<serde_json::Value>, JsErrorBox> {
+    let client = client(&state)?;
+    kv_get(&client, &namespace, &key).await.map_err(to_js_error)
+}
+
+#[op2(async)]
+async fn op_kv_set(
+    state: Rc<RefCell<OpState>>,
+    #[string] namespace: String,
+    #[string] key: String,
+    #[serde] value: serde_json::Value,
+    #[serde] ttl_ms: Option<i64>,
+) -> Result<(), JsErrorBox> {
+    let client = client(&state)?;
+    kv_set(&client, &namespace, &key, &value, ttl_ms)
+        .await
+        .map_err(to_js_error)
+}
+
+#[op2(async)]
+async fn op_kv_delete(
+    state: Rc<RefCell<OpState>>,
+    #[string] namespace: String,
+    #[string] key: String,
+) -> Result<bool, JsErrorBox> {
+    let client = client(&state)?;
+    kv_delete(&client, &namespace, &key)
+        .await
+        .map_err(to_js_error)
+}
+
+#[op2(async)]
+async fn op_kv_cas(
+    state: Rc<RefCell<OpState>>,
+    #[string] namespace: String,
+    #[string] key: String,
+    #[serde] expected: serde_json::Value,
+    #[serde] value: serde_json::Value,
+    #[serde] ttl_ms: Option<i64>,
+) -> Result<bool, JsErrorBox> {
+    let client = client(&state)?;
+    kv_cas(&client, &namespace, &key, &expected, &value, ttl_ms)
+        .await
+        .map_err(to_js_error)
+}
@@ -0,0 +1,515 @@
+use crate::modules::is_module;
+use crate::typescript::{transpile, TYPESCRIPT};
+use deno_ast::diagnostics::Diagnostic as _;
+use deno_ast::swc::ast::{
+    ArrowExpr, AssignTarget, BlockStmt, BlockStmtOrExpr, Class, Decl, DefaultDecl, ExportSpecifier,
+    Expr, Function, MemberProp, ModuleDecl, ModuleExportName, ModuleItem, ObjectLit, Pat, Prop,
+    PropName, PropOrSpread, ReturnStmt, SimpleAssignTarget, Stmt, VarDeclKind,
+};
+use deno_ast::swc::common::Span;
+use deno_ast::swc::ecma_visit::{Visit, VisitWith};
+use deno_ast::{
+    parse_program, MediaType, ModuleSpecifier, ParseDiagnostic, ParseParams, ParsedSource,
+    ProgramRef, SourcePos,
+};
+use serde::Serialize;
+
+/// A problem found in the script of a node, positions are 1-based.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl From<&ParseDiagnostic> for Diagnostic {
+    fn from(error: &ParseDiagnostic) -> Self {
+        let position = error.display_position();
+        Diagnostic {
+            line: position.line_number,
+            column: position.column_number,
+            message: error.message().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Checks the script of a node before it is saved. The script is parsed, none of it runs. A
+/// classic script has to declare a global `handle` function and a module has to export one as
+/// its default. The keys of object literals returned by `handle` are compared with the declared
+/// `outputs`.
+pub fn check_node(language: &str, script: &str, outputs: &[String]) -> Vec<Diagnostic> {
+    let media_type = if language == TYPESCRIPT {
+        MediaType::TypeScript
+    } else {
+        MediaType::JavaScript
+    };
+    let parsed = match parse_program(ParseParams {
+        specifier: ModuleSpecifier::parse("file:///node").unwrap(),
+        text: script.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    }) {
+        Ok(parsed) => parsed,
+        Err(error) => return vec![Diagnostic::from(&error)],
+    };
+    if !parsed.diagnostics().is_empty() {
+        return parsed.diagnostics().iter().map(Diagnostic::from).collect();
+    }
+
+    // The executor tells modules from classic scripts by the code it runs, which for TypeScript
+    // is the transpiled source
+    let module = if language == TYPESCRIPT {
+        match transpile(script) {
+            Ok(code) => is_module(&code),
+            Err(diagnostics) => return diagnostics,
+        }
+    } else {
+        is_module(script)
+    };
+
+    let checker = Checker { parsed: &parsed };
+    let handle = if module {
+        checker.default_export()
+    } else {
+        checker.global_handle()
+    };
+    match handle {
+        Ok(Some(body)) => checker.check_outputs(body, outputs),
+        Ok(None) => Vec::new(),
+        Err(diagnostic) => vec![diagnostic],
+    }
+}
+
+// Body of the `handle` function, either a block or the expression of an arrow function
+#[derive(Clone, Copy)]
+enum Body<'a> {
+    Block(&'a BlockStmt),
+    Expr(&'a Expr),
+}
+
+struct Checker<'a> {
+    parsed: &'a ParsedSource,
+}
+
+impl<'a> Checker<'a> {
+    fn diagnostic(&self, span: Span, message: &str) -> Diagnostic {
+        let position = self
+            .parsed
+            .text_info_lazy()
+            .line_and_column_display(SourcePos::unsafely_from_byte_pos(span.lo));
+        Diagnostic {
+            line: position.line_number,
+            column: position.column_number,
+            message: message.to_string(),
+        }
+    }
+
+    fn statements(&self) -> Vec<&'a Stmt> {
+        match self.parsed.program_ref() {
+            ProgramRef::Module(module) => module
+                .body
+                .iter()
+                .filter_map(|item| match item {
+                    ModuleItem::Stmt(statement) => Some(statement),
+                    ModuleItem::ModuleDecl(_) => None,
+                })
+                .collect(),
+            ProgramRef::Script(script) => script.body.iter().collect(),
+        }
+    }
+
+    // The global `handle` of a classic script, declared as a function, with `var` or assigned
+    // at the top level. `None` when it is not a function written out in the script.
+    fn global_handle(&self) -> Result<Option<Body<'a>>, Diagnostic> {
+        let mut lexical = None;
+        for statement in self.statements() {
+            match statement {
+                Stmt::Decl(Decl::Fn(function)) if &*function.ident.sym == "handle" => {
+                    return Ok(function_body(&function.function));
+                }
+                Stmt::Decl(Decl::Var(var)) => {
+                    for declarator in &var.decls {
+                        if !matches!(&declarator.name, Pat::Ident(name) if &*name.id.sym == "handle")
+                        {
+                            continue;
+                        }
+                        // `let` and `const` bindings are not properties of the global object,
+                        // the executor would not find them
+                        if var.kind != VarDeclKind::Var {
+                            lexical = Some(var.span);
+                            continue;
+                        }
+                        return Ok(declarator.init.as_deref().and_then(expr_body));
+                    }
+                }
+                Stmt::Expr(statement) => {
+                    if let Expr::Assign(assign) = &*statement.expr
+                        && assigns_global_handle(&assign.left)
+                    {
+                        return Ok(expr_body(&assign.right));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Err(match lexical {
+            Some(span) => self.diagnostic(
+                span,
+                "handle has to be declared as a function or with var to be called by the node",
+            ),
+            None => Diagnostic {
+                line: 1,
+                column: 1,
+                message: "Node script does not define a handle function".to_string(),
+            },
+        })
+    }
+
+    // The default export of a module. `None` when it is not a function written out in the
+    // module, such as a re-export of a library, which is only resolved when the node runs.
+    fn default_export(&self) -> Result<Option<Body<'a>>, Diagnostic> {
+        let ProgramRef::Module(module) = self.parsed.program_ref() else {
+            return Err(self.missing_default_export());
+        };
+        let export = module.body.iter().find_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => match &export.decl {
+                DefaultDecl::Fn(function) => Some(function_body(&function.function)),
+                _ => Some(None),
+            },
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => {
+                Some(match unparen(&export.expr) {
+                    Expr::Ident(ident) => self.declared_function(&ident.sym),
+                    expr => expr_body(expr),
+                })
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) => export
+                .specifiers
+                .iter()
+                .find_map(|specifier| match specifier {
+                    ExportSpecifier::Named(named)
+                        if is_default(named.exported.as_ref().unwrap_or(&named.orig)) =>
+                    {
+                        Some(match (&export.src, &named.orig) {
+                            (None, ModuleExportName::Ident(ident)) => {
+                                self.declared_function(&ident.sym)
+                            }
+                            _ => None,
+                        })
+                    }
+                    _ => None,
+                }),
+            _ => None,
+        });
+        export.ok_or_else(|| self.missing_default_export())
+    }
+
+    fn missing_default_export(&self) -> Diagnostic {
+        Diagnostic {
+            line: 1,
+            column: 1,
+            message: "Node module does not export a default handle function".to_string(),
+        }
+    }
+
+    // Body of the function declared at the top level under `name`
+    fn declared_function(&self, name: &str) -> Option<Body<'a>> {
+        self.statements()
+            .into_iter()
+            .find_map(|statement| match statement {
+                Stmt::Decl(Decl::Fn(function)) if &*function.ident.sym == name => {
+                    Some(function_body(&function.function))
+                }
+                Stmt::Decl(Decl::Var(var)) => var.decls.iter().find_map(|declarator| {
+                    matches!(&declarator.name, Pat::Ident(ident) if &*ident.id.sym == name)
+                        .then(|| declarator.init.as_deref().and_then(expr_body))
+                }),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    // Compares the keys of the object literals `handle` returns with the outputs of the node.
+    // Keys of returns from nested functions, spreads and computed keys cannot be known
+    // statically and are left alone.
+    fn check_outputs(&self, body: Body<'a>, outputs: &[String]) -> Vec<Diagnostic> {
+        let mut returns = Returns::default();
+        match body {
+            Body::Block(block) => block.visit_with(&mut returns),
+            Body::Expr(expr) => returns.add(expr),
+        }
+
+        let mut diagnostics = Vec::new();
+        for (span, key) in returns.keys {
+            if !outputs.contains(&key) {
+                diagnostics.push(self.diagnostic(
+                    span,
+                    &format!("Returned key {} is not a declared output", key),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+// Keys of the object literals returned by a function body, without entering nested functions
+// and classes
+#[derive(Default)]
+struct Returns {
+    keys: Vec<(Span, String)>,
+}
+
+impl Returns {
+    fn add(&mut self, expr: &Expr) {
+        if let Some(keys) = object_literal(expr).and_then(object_keys) {
+            self.keys.extend(keys);
+        }
+    }
+}
+
+impl Visit for Returns {
+    fn visit_return_stmt(&mut self, statement: &ReturnStmt) {
+        if let Some(arg) = &statement.arg {
+            self.add(arg);
+        }
+    }
+
+    fn visit_function(&mut self, _: &Function) {}
+
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+
+    fn visit_class(&mut self, _: &Class) {}
+}
+
+fn function_body(function: &Function) -> Option<Body<'_>> {
+    function.body.as_ref().map(Body::Block)
+}
+
+// Body of a function or arrow function expression
+fn expr_body(expr: &Expr) -> Option<Body<'_>> {
+    match unparen(expr) {
+        Expr::Fn(function) => function_body(&function.function),
+        Expr::Arrow(arrow) => Some(match &*arrow.body {
+            BlockStmtOrExpr::BlockStmt(block) => Body::Block(block),
+            BlockStmtOrExpr::Expr(expr) => Body::Expr(expr),
+        }),
+        _ => None,
+    }
+}
+
+fn unparen(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => unparen(&paren.expr),
+        expr => expr,
+    }
+}
+
+fn object_literal(expr: &Expr) -> Option<&ObjectLit> {
+    match unparen(expr) {
+        Expr::Object(object) => Some(object),
+        _ => None,
+    }
+}
+
+// `handle = ...`, `globalThis.handle = ...` or `this.handle = ...`
+fn assigns_global_handle(target: &AssignTarget) -> bool {
+    match target {
+        AssignTarget::Simple(SimpleAssignTarget::Ident(ident)) => &*ident.id.sym == "handle",
+        AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
+            let global = match &*member.obj {
+                Expr::Ident(ident) => &*ident.sym == "globalThis",
+                Expr::This(_) => true,
+                _ => false,
+            };
+            global && matches!(&member.prop, MemberProp::Ident(prop) if &*prop.sym == "handle")
+        }
+        _ => false,
+    }
+}
+
+fn is_default(name: &ModuleExportName) -> bool {
+    match name {
+        ModuleExportName::Ident(ident) => &*ident.sym == "default",
+        ModuleExportName::Str(name) => &*name.value == "default",
+    }
+}
+
+// Keys of an object literal with their spans, `None` when some keys are only known at runtime
+fn object_keys(object: &ObjectLit) -> Option<Vec<(Span, String)>> {
+    object
+        .props
+        .iter()
+        .map(|prop| match prop {
+            PropOrSpread::Spread(_) => None,
+            PropOrSpread::Prop(prop) => match &**prop {
+                Prop::Shorthand(ident) => Some((ident.span, ident.sym.to_string())),
+                Prop::KeyValue(prop) => prop_name(&prop.key),
+                Prop::Getter(prop) => prop_name(&prop.key),
+                Prop::Setter(prop) => prop_name(&prop.key),
+                Prop::Method(prop) => prop_name(&prop.key),
+                Prop::Assign(_) => None,
+            },
+        })
+        .collect()
+}
+
+fn prop_name(name: &PropName) -> Option<(Span, String)> {
+    match name {
+        PropName::Ident(ident) => Some((ident.span, ident.sym.to_string())),
+        PropName::Str(name) => Some((name.span, name.value.to_string())),
+        PropName::Num(number) => Some((number.span, number.value.to_string())),
+        PropName::BigInt(number) => Some((number.span, number.value.to_string())),
+        PropName::Computed(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(language: &str, script: &str, outputs: &[&str]) -> Vec<String> {
+        let outputs: Vec<String> = outputs.iter().map(|output| output.to_string()).collect();
+        check_node(language, script, &outputs)
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_global_handle_functions() {
+        let scripts = [
+            "function handle(input) { return { out: input.value }; }",
+            "var handle = async (input) => ({ out: input.value });",
+            "handle = function (input) { return { out: 1 }; };",
+            "globalThis.handle = (input) => { return { out: 1 }; };",
+        ];
+        for script in scripts {
+            assert!(
+                check("javascript", script, &["out"]).is_empty(),
+                "{}",
+                script
+            );
+        }
+    }
+
+    #[test]
+    fn reports_missing_handle() {
+        assert_eq!(
+            check("javascript", "function run(input) { return {}; }", &[]),
+            vec!["1:1: Node script does not define a handle function"]
+        );
+        assert_eq!(
+            check("javascript", "\nconst handle = (input) => ({});", &[]),
+            vec![
+                "2:1: handle has to be declared as a function or with var to be called by the node"
+            ]
+        );
+        assert_eq!(
+            check(
+                "javascript",
+                "export function handle(input) { return {}; }",
+                &[]
+            ),
+            vec!["1:1: Node module does not export a default handle function"]
+        );
+    }
+
+    #[test]
+    fn does_not_run_the_script() {
+        let script =
+            "while (true) {}\nthrow new Error('top level');\nfunction handle() { return {}; }";
+        assert!(check("javascript", script, &[]).is_empty());
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(
+            check(
+                "javascript",
+                "function handle(input) {\n  return {;\n}",
+                &[]
+            )
+            .len(),
+            1
+        );
+        assert_eq!(
+            check(
+                "typescript",
+                "function handle(input: Input {\n  return {};\n}",
+                &[]
+            ),
+            vec!["1:30: Expected ',', got '{'"]
+        );
+    }
+
+    #[test]
+    fn compares_returned_keys_with_outputs() {
+        let script = r#"function handle(input) {
+  if (input.ok) {
+    return { value: 1, "label": "a", 2: true };
+  }
+  const nested = () => ({ ignored: true });
+  return { value: 2, extra: 3 };
+}"#;
+        assert_eq!(
+            check("javascript", script, &["value", "label"]),
+            vec![
+                "3:38: Returned key 2 is not a declared output",
+                "6:22: Returned key extra is not a declared output",
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_dynamic_keys_alone() {
+        let script = r#"function handle(input) {
+  return { ...input, [input.key]: 1, value: 2 };
+}"#;
+        assert!(check("javascript", script, &[]).is_empty());
+    }
+
+    #[test]
+    fn checks_default_exports_of_modules() {
+        let script = r#"import { slugify } from "text-utils";
+
+export default async function handle(input) {
+  return { slug: slugify(input.title), title: input.title };
+}"#;
+        assert_eq!(
+            check("javascript", script, &["slug"]),
+            vec!["4:40: Returned key title is not a declared output"]
+        );
+
+        let script = r#"const handle = (input) => ({ slug: input.title, other: 1 });
+export { handle as default };"#;
+        assert_eq!(
+            check("javascript", script, &["slug"]),
+            vec!["1:49: Returned key other is not a declared output"]
+        );
+
+        let script = r#"export { default } from "text-utils";"#;
+        assert!(check("javascript", script, &[]).is_empty());
+    }
+
+    #[test]
+    fn checks_typescript_sources() {
+        let script = r#"interface Result { value: number }
+
+function handle(input: Input): Result & { extra: string } {
+  return { value: input.count as number, extra: "a" };
+}"#;
+        assert_eq!(
+            check("typescript", script, &["value"]),
+            vec!["4:42: Returned key extra is not a declared output"]
+        );
+    }
+}
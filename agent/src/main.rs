@@ -1,8 +1,19 @@
 pub(crate) mod blocks;
 mod db;
+mod debugger;
+mod egress;
+mod events;
 mod executor;
 mod routes;
 mod middlewares;
+mod kv;
+mod lint;
+mod modules;
+mod sandbox;
+mod secrets;
+mod testing;
+mod typescript;
+mod validation;
 
 use crate::middlewares::db_logging::DbLogger;
 use actix_cors::Cors;
@@ -54,6 +65,11 @@ async fn main() -> anyhow::Result<()> {
     });
     let client = Arc::new(client);
 
+    // Register the built-in blocks as internal nodes
+    if let Err(e) = blocks::seed_blocks(&client).await {
+        error!("Failed to seed built-in blocks: {}", e);
+    }
+
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
@@ -63,6 +79,7 @@ async fn main() -> anyhow::Result<()> {
                     .allow_any_origin()
                     .allow_any_method()
                     .allow_any_header()
+                    .expose_headers(["X-Next-Cursor"])
                     .max_age(3600),
             )
             .wrap(DbLogger {
@@ -0,0 +1,244 @@
+use crate::db::libraries::get_library_by_name;
+use deno_ast::swc::ast::{
+    CallExpr, Callee, ExportAll, Expr, ImportDecl, Lit, ModuleItem, NamedExport,
+};
+use deno_ast::swc::ecma_visit::{Visit, VisitWith};
+use deno_ast::{parse_program, MediaType, ParseParams, ParsedSource, ProgramRef};
+use deno_core::error::ModuleLoaderError;
+use deno_core::{
+    ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
+    RequestedModuleType, ResolutionKind,
+};
+use deno_error::JsErrorBox;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+const LIBRARY_SCHEME: &str = "lib";
+const SCRIPT_SCHEME: &str = "script";
+
+/// Whether a node script is written as an ES module rather than a classic script defining a
+/// global `handle`, i.e. whether it declares imports or exports. `import()` calls are allowed in
+/// classic scripts too. A script that does not parse is run as a classic script, which reports
+/// the syntax error.
+pub fn is_module(source: &str) -> bool {
+    parse(source).is_some_and(|parsed| match parsed.program_ref() {
+        ProgramRef::Module(module) => module
+            .body
+            .iter()
+            .any(|item| matches!(item, ModuleItem::ModuleDecl(_))),
+        ProgramRef::Script(_) => false,
+    })
+}
+
+/// Names of the shared libraries a script imports, through `import` and `export ... from`
+/// declarations or `import()` with a literal specifier. A script that does not parse imports
+/// nothing.
+pub fn imported_libraries(source: &str) -> Vec<String> {
+    let Some(parsed) = parse(source) else {
+        return Vec::new();
+    };
+    let mut imports = Imports(Vec::new());
+    parsed.program_ref().visit_with(&mut imports);
+    imports.0
+}
+
+fn parse(source: &str) -> Option<ParsedSource> {
+    parse_program(ParseParams {
+        specifier: ModuleSpecifier::parse("file:///script.js").unwrap(),
+        text: source.into(),
+        media_type: MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .ok()
+}
+
+// The library a specifier resolves to, like `DbModuleLoader::resolve` does
+fn library_name(specifier: &str) -> Option<String> {
+    match ModuleSpecifier::parse(specifier) {
+        Ok(specifier) if specifier.scheme() == LIBRARY_SCHEME => Some(specifier.path().to_string()),
+        Ok(_) => None,
+        Err(_) => Some(specifier.to_string()),
+    }
+}
+
+struct Imports(Vec<String>);
+
+impl Imports {
+    fn add(&mut self, specifier: &str) {
+        if let Some(name) = library_name(specifier)
+            && !self.0.contains(&name)
+        {
+            self.0.push(name);
+        }
+    }
+}
+
+impl Visit for Imports {
+    fn visit_import_decl(&mut self, import: &ImportDecl) {
+        self.add(&import.src.value);
+    }
+
+    fn visit_export_all(&mut self, export: &ExportAll) {
+        self.add(&export.src.value);
+    }
+
+    fn visit_named_export(&mut self, export: &NamedExport) {
+        if let Some(src) = &export.src {
+            self.add(&src.value);
+        }
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let (Callee::Import(_), Some(arg)) = (&call.callee, call.args.first())
+            && let Expr::Lit(Lit::Str(specifier)) = &*arg.expr
+        {
+            self.add(&specifier.value);
+        }
+        call.visit_children_with(self);
+    }
+}
+
+/// Specifier under which the module of a node script is registered.
+pub fn script_specifier(node_id: Uuid) -> ModuleSpecifier {
+    ModuleSpecifier::parse(&format!("{}:{}", SCRIPT_SCHEME, node_id)).unwrap()
+}
+
+/// Resolves imports of node modules to the shared libraries stored in the database, a bare
+/// specifier such as `"utils"` names a library. Nothing is read from the filesystem or the
+/// network.
+pub struct DbModuleLoader {
+    client: Arc<Client>,
+}
+
+impl DbModuleLoader {
+    pub fn new(client: Arc<Client>) -> Self {
+        DbModuleLoader { client }
+    }
+}
+
+impl ModuleLoader for DbModuleLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        _kind: ResolutionKind,
+    ) -> Result<ModuleSpecifier, ModuleLoaderError> {
+        let specifier = match ModuleSpecifier::parse(specifier) {
+            Ok(specifier) => specifier,
+            Err(_) => ModuleSpecifier::parse(&format!("{}:{}", LIBRARY_SCHEME, specifier))
+                .map_err(|_| {
+                    JsErrorBox::type_error(format!("Invalid library name: {}", specifier))
+                })?,
+        };
+
+        match specifier.scheme() {
+            LIBRARY_SCHEME => Ok(specifier),
+            // Node scripts are loaded by the executor as roots, they cannot import each other
+            SCRIPT_SCHEME if referrer == "." => Ok(specifier),
+            _ => Err(JsErrorBox::type_error(format!(
+                "Cannot import {}, only shared libraries can be imported",
+                specifier
+            ))
+            .into()),
+        }
+    }
+
+    fn load(
+        &self,
+        module_specifier: &ModuleSpecifier,
+        _maybe_referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        _requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        if module_specifier.scheme() != LIBRARY_SCHEME {
+            return ModuleLoadResponse::Sync(Err(ModuleLoaderError::NotFound));
+        }
+
+        let client = self.client.clone();
+        let specifier = module_specifier.clone();
+        ModuleLoadResponse::Async(Box::pin(async move {
+            let name = specifier.path();
+            let library = get_library_by_name(&client, name)
+                .await
+                .map_err(|e| {
+                    JsErrorBox::generic(format!("Failed to load library {}: {}", name, e))
+                })?
+                .ok_or_else(|| {
+                    JsErrorBox::type_error(format!("Library {} does not exist", name))
+                })?;
+
+            Ok(ModuleSource::new(
+                ModuleType::JavaScript,
+                ModuleSourceCode::String(library.source.into()),
+                &specifier,
+                None,
+            ))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_declare_imports_or_exports() {
+        for source in [
+            "export default function handle() {}",
+            "import { format } from \"utils\";\nfunction handle() {}",
+            "  export{ handle as default };\nfunction handle() {}",
+            "import\"setup\";\nfunction handle() {}",
+        ] {
+            assert!(is_module(source), "{} is not a module", source);
+        }
+    }
+
+    #[test]
+    fn classic_scripts_may_mention_imports_and_exports() {
+        for source in [
+            "function handle() {\n  return { value: `\nimport x from \"y\";\nexport default 1` };\n}",
+            "// export default handle;\nfunction handle() {}",
+            "/*\nimport { a } from \"b\";\n*/\nfunction handle() {}",
+            "async function handle() {\n  const { get } = await import(\"client\");\n}",
+            "function handle( {",
+        ] {
+            assert!(!is_module(source), "{} is a module", source);
+        }
+    }
+
+    #[test]
+    fn finds_imported_libraries() {
+        let source = r#"
+            import { format } from "utils";
+            import * as dates from "lib:dates";
+            export { parse } from "parsers";
+            export * from "utils";
+            import remote from "https://example.com/module.js";
+            export default async function handle() {
+                const lazy = await import("lazy");
+                return { value: format(await import(name)) };
+            }
+        "#;
+
+        assert_eq!(
+            imported_libraries(source),
+            vec!["utils", "dates", "parsers", "lazy"]
+        );
+    }
+
+    #[test]
+    fn classic_scripts_import_through_import_calls() {
+        let source = r#"
+            // import "commented";
+            async function handle() {
+                const { get } = await import("lib:client");
+                return { value: `import "template"` };
+            }
+        "#;
+
+        assert_eq!(imported_libraries(source), vec!["client"]);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::db::environments;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct EnvironmentRequest {
+    name: String,
+    #[serde(default = "empty_variables")]
+    variables: Value,
+}
+
+fn empty_variables() -> Value {
+    Value::Object(Default::default())
+}
+
+#[derive(Deserialize)]
+pub struct ListEnvironmentsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[post("")]
+pub async fn create_environment(
+    client: web::Data<Arc<Client>>,
+    req: web::Json<EnvironmentRequest>,
+) -> impl Responder {
+    if !req.variables.is_object() {
+        return HttpResponse::BadRequest().body("Variables must be an object");
+    }
+
+    match environments::create_environment(&client, &req.name, &req.variables).await {
+        Ok(environment) => HttpResponse::Created().json(environment),
+        Err(e) => HttpResponse::BadRequest().body(format!("Failed to create environment: {}", e)),
+    }
+}
+
+#[get("/{id}")]
+pub async fn get_environment(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match environments::get_environment(&client, id.into_inner()).await {
+        Ok(Some(environment)) => HttpResponse::Ok().json(environment),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[put("/{id}")]
+pub async fn update_environment(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<EnvironmentRequest>,
+) -> impl Responder {
+    if !req.variables.is_object() {
+        return HttpResponse::BadRequest().body("Variables must be an object");
+    }
+
+    match environments::update_environment(&client, id.into_inner(), &req.name, &req.variables).await {
+        Ok(Some(environment)) => HttpResponse::Ok().json(environment),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::BadRequest().body(format!("Failed to update environment: {}", e)),
+    }
+}
+
+#[delete("/{id}")]
+pub async fn delete_environment(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match environments::delete_environment(&client, id.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+pub async fn list_environments(
+    client: web::Data<Arc<Client>>,
+    query: web::Query<ListEnvironmentsQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(10);
+    let offset = query.offset.unwrap_or(0);
+
+    match environments::list_environments(&client, limit, offset).await {
+        Ok(environments) => HttpResponse::Ok().json(environments),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/environments")
+            .service(create_environment)
+            .service(list_environments)
+            .service(get_environment)
+            .service(update_environment)
+            .service(delete_environment),
+    );
+}
@@ -0,0 +1,56 @@
+use crate::events::{self, Event};
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpResponse, Responder};
+use futures_util::stream;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+// Idle streams send a comment this often so proxies keep them open
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventsQuery {
+    pipeline_id: Option<Uuid>,
+    history_id: Option<Uuid>,
+}
+
+impl EventsQuery {
+    // Logs carry the pipeline and history of the run that wrote them and match like any other
+    // event, logs written outside of a run only reach streams that do not filter on one
+    fn matches(&self, event: &Event) -> bool {
+        self.pipeline_id.is_none_or(|id| event.pipeline_id == Some(id))
+            && self.history_id.is_none_or(|id| event.history_id == Some(id))
+    }
+}
+
+// Streams events as they happen as server-sent events, one JSON object per event
+#[get("")]
+pub async fn stream_events(query: web::Query<EventsQuery>) -> impl Responder {
+    let receiver = events::subscribe();
+    let events = stream::unfold((receiver, query.into_inner()), |(mut receiver, query)| async move {
+        loop {
+            let chunk = match tokio::time::timeout(KEEP_ALIVE, receiver.recv()).await {
+                Ok(Ok(event)) if query.matches(&event) => match serde_json::to_string(&event) {
+                    Ok(event) => format!("data: {}\n\n", event),
+                    Err(_) => continue,
+                },
+                Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => continue,
+                Ok(Err(RecvError::Closed)) => return None,
+                Err(_) => ": keep-alive\n\n".to_string(),
+            };
+            return Some((Ok::<_, actix_web::Error>(Bytes::from(chunk)), (receiver, query)));
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/events").service(stream_events));
+}
@@ -1,13 +1,24 @@
-use crate::db::flow::Graph;
-use crate::db::history::{create_history, fail_history, success_history, update_history_status};
-use crate::db::{create_log, get_pipeline, LogLevel};
-use crate::executor::GraphExecutor;
+use crate::db::flow::{Graph, GraphNode, GraphNodeData, GraphNodeMeasured, GraphNodePosition};
+use crate::db::history::{
+    create_history, fail_history, success_history, update_history_status,
+    update_history_trace,
+};
+use crate::db::kv::clear_namespace;
+use crate::db::models::{History, Node, Pipeline};
+use crate::db::{create_pipeline_log, get_pipeline, LogLevel};
+use crate::events::{self, EventKind};
+use crate::executor::{redact_error, ConsoleEntry, GraphExecutor, ScriptError};
 use actix_web::{route, web, HttpRequest, HttpResponse, Responder};
+use deno_core::error::AnyError;
 use log::{info, warn};
-use serde_json::Value;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
 use std::sync::Arc;
 use tokio_postgres::Client;
 use uuid::Uuid;
+use crate::db::environments::get_environment;
 use crate::db::routes::find_route;
 
 #[route(
@@ -31,67 +42,556 @@ pub async fn exec(
     if let Err(e) = route_result {
         return HttpResponse::NotFound().body(format!("{:?}", e));
     }
-    let pipeline_id: Uuid = route_result.unwrap().pipeline_id;
+    let route = route_result.unwrap();
+    let env = match route.environment_id {
+        Some(environment_id) => match get_environment(&client, environment_id).await {
+            Ok(Some(environment)) => environment.variables,
+            Ok(None) => return HttpResponse::InternalServerError().body(format!("Environment {} of the route does not exist", environment_id)),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+        None => json!({}),
+    };
+    let pipeline_id: Uuid = route.pipeline_id;
     let pipeline_result = get_pipeline(&client, pipeline_id).await;
     if let Err(e) = pipeline_result {
         return HttpResponse::NotFound().body(format!("{:?}", e));
     }
-    let pipeline_result = pipeline_result.unwrap().unwrap();
-    
-    let pipeline_name: String = pipeline_result.name;
-    let pipeline_graph_item = pipeline_result.content;
-    let pipeline_graph: Graph = serde_json::from_value(pipeline_graph_item).unwrap();
-    
-    let history = create_history(&client, pipeline_id, "preparing").await;
-    if let Err(e) = history {
+    let pipeline = pipeline_result.unwrap().unwrap();
+
+    let input = json.into_inner();
+    // Errors come back with secrets redacted
+    match run_pipeline(&client, &pipeline, input.clone(), &env, route.environment_id).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) => match pipeline.error_handler_id {
+            Some(handler_id) => run_error_handler(&client, handler_id, &e, input, &env, route.environment_id).await,
+            None => HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    }
+}
+
+/// Executes a pipeline graph from `BeginRequest` to `EndRequest`, recording its history and
+/// execution trace. `env` holds the variables of the environment `environment_id` the pipeline
+/// runs in. Errors are returned with secrets redacted, so they can be passed on to clients.
+pub async fn run_pipeline(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: Value,
+    env: &Value,
+    environment_id: Option<Uuid>,
+) -> Result<Value, AnyError> {
+    let history = create_run_history(client, pipeline, &input, environment_id, None).await?;
+    execute_pipeline(client, pipeline, input, env, history.id).await
+}
+
+/// Creates the history of a run of a pipeline along with its request and the revision of the
+/// pipeline, so the run can be replayed. Only the id of the environment is recorded, its
+/// variables may hold credentials and stay out of history.
+pub async fn create_run_history(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: &Value,
+    environment_id: Option<Uuid>,
+    replay_of: Option<Uuid>,
+) -> Result<History, AnyError> {
+    let context = json!({ "environmentId": environment_id });
+    let revision = serde_json::to_value(pipeline)?;
+    let history = create_history(client, pipeline.id, "preparing", input, &context, &revision, replay_of).await;
+    if let Err(e) = &history {
         warn!("Failed to create history: {:?}", e);
-        create_log(&client, LogLevel::Error, "Execution", "Failed to create history").await.unwrap();
-        
-        return HttpResponse::InternalServerError().body(format!("{:?}", e));
+        create_pipeline_log(client, LogLevel::Error, "Execution", "Failed to create history", pipeline.id, None).await.unwrap();
     }
-    let history = history.unwrap();
-    
+    history
+}
+
+/// Executes a pipeline graph like `run_pipeline`, recording the run in an existing history.
+/// Errors are returned with secrets redacted.
+pub async fn execute_pipeline(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: Value,
+    env: &Value,
+    history_id: Uuid,
+) -> Result<Value, AnyError> {
+    events::publish(Some(pipeline.id), Some(history_id), EventKind::ExecutionStarted);
+    let result = record_execution(client, pipeline, input, env, history_id).await;
+
+    let kind = match &result {
+        Ok(_) => EventKind::ExecutionFinished,
+        Err(e) => EventKind::ExecutionFailed { error: e.to_string() },
+    };
+    events::publish(Some(pipeline.id), Some(history_id), kind);
+
+    result
+}
+
+async fn record_execution(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: Value,
+    env: &Value,
+    history_id: Uuid,
+) -> Result<Value, AnyError> {
+    let pipeline_name = &pipeline.name;
+    let pipeline_graph: Graph = match serde_json::from_value(pipeline.content.clone()) {
+        Ok(pipeline_graph) => pipeline_graph,
+        Err(e) => {
+            fail_history(client, history_id, &format!("Invalid pipeline graph: {}", e)).await.unwrap();
+
+            return Err(e.into());
+        }
+    };
+
     info!("Initializing GraphExecutor for pipeline graph: {}", pipeline_name);
-    create_log(&client, LogLevel::Info, "Execution", &format!("Initializing GraphExecutor for pipeline graph: {}", pipeline_name)).await.unwrap();
-    
-    let mut executor = GraphExecutor::new(pipeline_graph, &client).await.unwrap();
-    if let Err(e) = executor.init_entry(json.into_inner()) {
+    create_pipeline_log(client, LogLevel::Info, "Execution", &format!("Initializing GraphExecutor for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+
+    // The executor redacts the errors of its own setup
+    let executor = GraphExecutor::new(pipeline_graph, client).await;
+    if let Err(e) = executor {
+        warn!("Failed to create GraphExecutor for pipeline graph: {}", pipeline_name);
+        create_pipeline_log(client, LogLevel::Error, "Execution", &format!("Failed to create GraphExecutor for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+        fail_history(client, history_id, e.to_string().as_str()).await.unwrap();
+
+        return Err(e);
+    }
+    let mut executor = executor.unwrap();
+    executor.pipeline_id = Some(pipeline.id);
+    executor.history_id = Some(history_id);
+    executor.validate_types = env::var("VALIDATE_PORT_TYPES").is_ok_and(|value| value == "true");
+    executor.allowed_hosts = pipeline.allowed_hosts.clone();
+    executor.env = env.clone();
+    executor.kv_namespace = pipeline.id.to_string();
+    if let Some(network_policy) = &pipeline.network_policy {
+        match serde_json::from_value(network_policy.clone()) {
+            Ok(network_policy) => executor.add_network_policy(network_policy),
+            Err(e) => {
+                warn!("Invalid network policy for pipeline graph: {}", pipeline_name);
+                fail_history(client, history_id, &format!("Invalid network policy: {}", e)).await.unwrap();
+
+                return Err(e.into());
+            }
+        }
+    }
+    if let Err(e) = executor.init_entry(input) {
+        let e = redact_error(executor.secrets(), e);
         warn!("Failed to initialize GraphExecutor for pipeline graph: {}", pipeline_name);
-        create_log(&client, LogLevel::Error, "Execution", &format!("Failed to initialize GraphExecutor for pipeline graph: {}", pipeline_name)).await.unwrap();
-        fail_history(&client, history.id, e.to_string().as_str()).await.unwrap();
-        
-        return HttpResponse::InternalServerError().body("Failed to initialize pipeline entry");
+        create_pipeline_log(client, LogLevel::Error, "Execution", &format!("Failed to initialize GraphExecutor for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+        fail_history(client, history_id, &e.to_string()).await.unwrap();
+
+        return Err(e);
     }
 
     executor.init_node_queue();
-    update_history_status(&client, history.id, "running").await.unwrap();
-    
+    update_history_status(client, history_id, "running").await.unwrap();
+
     while !executor.reached_end {
-        if let Err(e) = executor.exec_current_queue() {
+        let result = executor.exec_current_queue().await;
+        for denial in executor.take_network_denials() {
+            let denial = executor.secrets().redact(&denial);
+            warn!("{} in pipeline graph: {}", denial, pipeline_name);
+            create_pipeline_log(client, LogLevel::Warn, "Network", &format!("{} in pipeline graph: {}", denial, pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+        }
+        if let Err(e) = result.and_then(|_| executor.update_next_node_queue()) {
+            let e = redact_error(executor.secrets(), e);
             warn!("Failed to execute current queue for pipeline graph: {}", pipeline_name);
-            create_log(&client, LogLevel::Error, "Execution", &format!("Failed to execute current queue for pipeline graph: {}", pipeline_name)).await.unwrap();
-            fail_history(&client, history.id, e.to_string().as_str()).await.unwrap();
-            
-            return HttpResponse::InternalServerError().body("Failed to execute current queue");
+            create_pipeline_log(client, LogLevel::Error, "Execution", &format!("Failed to execute current queue for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+            update_history_trace(client, history_id, &executor.trace_json()).await.unwrap();
+            fail_history(client, history_id, &e.to_string()).await.unwrap();
+
+            return Err(e);
         }
 
-        executor.update_next_node_queue();
         executor.apply_next_queue();
+
+        if executor.current_node_queue.is_empty() && !executor.reached_end {
+            warn!("Pipeline graph stopped before reaching EndRequest: {}", pipeline_name);
+            create_pipeline_log(client, LogLevel::Error, "Execution", &format!("Pipeline graph stopped before reaching EndRequest: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+            update_history_trace(client, history_id, &executor.trace_json()).await.unwrap();
+            fail_history(client, history_id, "Pipeline stopped before reaching EndRequest").await.unwrap();
+
+            return Err(AnyError::msg("Pipeline stopped before reaching EndRequest"));
+        }
     }
+    update_history_trace(client, history_id, &executor.trace_json()).await.unwrap();
 
     let result = executor.get_result();
     if let Err(e) = result {
+        let e = redact_error(executor.secrets(), e);
         warn!("Failed to get execution result for pipeline graph: {}", pipeline_name);
-        create_log(&client, LogLevel::Error, "Execution", &format!("Failed to get execution result for pipeline graph: {}", pipeline_name)).await.unwrap();
-        fail_history(&client, history.id, e.to_string().as_str()).await.unwrap();
-        
-        return HttpResponse::InternalServerError().body("Failed to get execution result");
+        create_pipeline_log(client, LogLevel::Error, "Execution", &format!("Failed to get execution result for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+        fail_history(client, history_id, &e.to_string()).await.unwrap();
+
+        return Err(e);
     }
     let result = result.unwrap();
 
     info!("Execution completed for pipeline graph: {}", pipeline_name);
-    create_log(&client, LogLevel::Info, "Execution", &format!("Execution completed for pipeline graph: {}", pipeline_name)).await.unwrap();
-    success_history(&client, history.id, Some(result.clone())).await.unwrap();
-    
-    HttpResponse::Ok().json(result)
+    create_pipeline_log(client, LogLevel::Info, "Execution", &format!("Execution completed for pipeline graph: {}", pipeline_name), pipeline.id, Some(history_id)).await.unwrap();
+    success_history(client, history_id, Some(executor.secrets().redact_value(result.clone()))).await.unwrap();
+
+    Ok(result)
+}
+
+/// Sets up an executor for a pipeline graph like `run_pipeline` does, for runs that are not
+/// recorded in history. Such runs publish no events.
+pub async fn pipeline_executor(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    env: &Value,
+) -> Result<GraphExecutor, AnyError> {
+    let pipeline_graph: Graph = serde_json::from_value(pipeline.content.clone())?;
+    let mut executor = GraphExecutor::new(pipeline_graph, client).await?;
+    executor.validate_types = env::var("VALIDATE_PORT_TYPES").is_ok_and(|value| value == "true");
+    executor.allowed_hosts = pipeline.allowed_hosts.clone();
+    executor.env = env.clone();
+    executor.kv_namespace = pipeline.id.to_string();
+    if let Some(network_policy) = &pipeline.network_policy {
+        executor.add_network_policy(serde_json::from_value(network_policy.clone())?);
+    }
+    Ok(executor)
+}
+
+/// A `kv` namespace of its own for a run that has to leave the store of its pipeline alone,
+/// removed with `remove_scratch_namespace` once the run is over.
+pub fn scratch_namespace() -> String {
+    format!("scratch:{}", Uuid::new_v4())
+}
+
+/// Removes every entry a run wrote to its scratch namespace.
+pub async fn remove_scratch_namespace(client: &Client, namespace: &str) {
+    if let Err(e) = clear_namespace(client, namespace).await {
+        warn!("Failed to remove scratch kv namespace {}: {}", namespace, e);
+    }
+}
+
+/// Drives an executor from `BeginRequest` to `EndRequest` and returns the result of the graph.
+pub async fn run_graph(executor: &mut GraphExecutor, input: Value) -> Result<Value, AnyError> {
+    executor.init_entry(input)?;
+    executor.init_node_queue();
+    while !executor.reached_end {
+        executor.exec_current_queue().await?;
+        executor.update_next_node_queue()?;
+        executor.apply_next_queue();
+
+        if executor.current_node_queue.is_empty() && !executor.reached_end {
+            return Err(AnyError::msg("Pipeline stopped before reaching EndRequest"));
+        }
+    }
+    executor.get_result()
+}
+
+/// Converts an execution error to JSON, keeping the details of exceptions thrown by scripts.
+pub fn error_json(error: &AnyError) -> Value {
+    match error.downcast_ref::<ScriptError>() {
+        Some(script_error) => serde_json::to_value(script_error).unwrap(),
+        None => json!({ "message": error.to_string() }),
+    }
+}
+
+/// Outcome of a dry run of a pipeline.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun {
+    /// Value reaching `EndRequest`.
+    pub result: Option<Value>,
+    pub error: Option<Value>,
+    pub trace: Value,
+    pub console: Vec<ConsoleEntry>,
+}
+
+/// Runs a pipeline without recording history or logs. Nodes in `mocks` return the given
+/// outputs instead of running, which keeps their side effects out of the run. Scripts use a
+/// scratch `kv` namespace instead of the one of the pipeline. Secrets are redacted from
+/// everything the run returns.
+pub async fn dry_run(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    input: Value,
+    env: &Value,
+    mocks: HashMap<String, Value>,
+) -> Result<DryRun, AnyError> {
+    let mut executor = pipeline_executor(client, pipeline, env).await?;
+    let namespace = scratch_namespace();
+    executor.kv_namespace = namespace.clone();
+    let run = dry_run_executor(executor, input, mocks).await;
+    remove_scratch_namespace(client, &namespace).await;
+    Ok(run)
+}
+
+/// Runs a dry run like `dry_run` with an executor the caller has set up, including its `kv`
+/// namespace.
+pub async fn dry_run_executor(
+    mut executor: GraphExecutor,
+    input: Value,
+    mocks: HashMap<String, Value>,
+) -> DryRun {
+    executor.mocks = mocks;
+    let result = run_graph(&mut executor, input).await;
+
+    let secrets = executor.secrets();
+    let error = result.as_ref().err().map(|e| secrets.redact_value(error_json(e)));
+    DryRun {
+        result: result.ok().map(|result| secrets.redact_value(result)),
+        error,
+        trace: executor.trace_json(),
+        console: redacted_console(&executor),
+    }
+}
+
+/// Outcome of running a single node on its own.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeRun {
+    pub outputs: Option<Value>,
+    pub console: Vec<ConsoleEntry>,
+    pub duration_ms: i64,
+    pub error: Option<Value>,
+}
+
+/// Runs a node outside of any pipeline through the executor, without recording history. The
+/// node uses its own `kv` namespace and secrets are redacted from everything it returns.
+pub async fn run_node(
+    client: &Arc<Client>,
+    node: &Node,
+    inputs: Map<String, Value>,
+    config: Option<Value>,
+) -> Result<NodeRun, AnyError> {
+    let graph_node_id = "node";
+    let graph = Graph {
+        nodes: vec![GraphNode {
+            id: graph_node_id.to_string(),
+            node_type: "flowNode".to_string(),
+            position: GraphNodePosition { x: 0.0, y: 0.0 },
+            data: GraphNodeData {
+                id: node.id.to_string(),
+                name: node.name.clone(),
+                inputs: node.inputs.clone(),
+                outputs: node.outputs.clone(),
+                config,
+                join: Default::default(),
+                retry: None,
+                network: None,
+            },
+            measured: GraphNodeMeasured { width: 0.0, height: 0.0 },
+            selected: false,
+            dragging: false,
+        }],
+        edges: vec![],
+    };
+
+    let mut executor = GraphExecutor::new(graph, client).await?;
+    executor.kv_namespace = node.id.to_string();
+    let result = executor.run_node(graph_node_id, inputs).await;
+
+    let secrets = executor.secrets();
+    let error = result.as_ref().err().map(|e| secrets.redact_value(error_json(e)));
+    Ok(NodeRun {
+        outputs: result.ok().map(|outputs| secrets.redact_value(outputs)),
+        console: redacted_console(&executor),
+        duration_ms: executor.trace.iter().map(|step| step.duration_ms).sum(),
+        error,
+    })
+}
+
+fn redacted_console(executor: &GraphExecutor) -> Vec<ConsoleEntry> {
+    executor
+        .console
+        .iter()
+        .map(|entry| ConsoleEntry {
+            message: executor.secrets().redact(&entry.message),
+            ..entry.clone()
+        })
+        .collect()
+}
+
+// Runs the error handler pipeline of a failed pipeline, its result becomes the response.
+// `error` comes from `run_pipeline` and has its secrets redacted already.
+async fn run_error_handler(
+    client: &Arc<Client>,
+    handler_id: Uuid,
+    error: &AnyError,
+    input: Value,
+    env: &Value,
+    environment_id: Option<Uuid>,
+) -> HttpResponse {
+    let handler = match get_pipeline(client, handler_id).await {
+        Ok(Some(handler)) => handler,
+        _ => {
+            warn!("Error handler pipeline not found: {}", handler_id);
+            return HttpResponse::InternalServerError().body(error.to_string());
+        }
+    };
+
+    let handler_input = json!({ "error": error_json(error), "data": input });
+
+    handler_response(run_pipeline(client, &handler, handler_input, env, environment_id).await)
+}
+
+// A handler that reaches EndRequest has handled the error, its result is answered like that of
+// any pipeline. Only a failing handler leaves the request failed.
+fn handler_response(result: Result<Value, AnyError>) -> HttpResponse {
+    match result {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::kv::kv_get;
+    use crate::db::nodes::{create_node, delete_node};
+    use crate::db::upsert_internal_node;
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use tokio_postgres::{Config, NoTls};
+
+    // Connects to the database like the agent does, it needs `sql/schema.sql` loaded
+    async fn connect() -> Arc<Client> {
+        let mut cfg = Config::new();
+        cfg.host(env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string()))
+            .port(env::var("DB_PORT").map_or(5432, |port| port.parse().unwrap()))
+            .dbname(env::var("DB_NAME").unwrap_or_else(|_| "lambda-nodes".to_string()))
+            .user(env::var("DB_USER").unwrap_or_else(|_| "postgres".to_string()))
+            .password(env::var("DB_PASSWORD").unwrap_or_else(|_| "postgres".to_string()));
+        let (client, connection) = cfg.connect(NoTls).await.unwrap();
+        tokio::spawn(connection);
+        Arc::new(client)
+    }
+
+    async fn internal_node(
+        client: &Client,
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+    ) -> Uuid {
+        let ports = |ports: &[&str]| {
+            ports
+                .iter()
+                .map(|port| port.to_string())
+                .collect::<Vec<_>>()
+        };
+        upsert_internal_node(
+            client,
+            name,
+            &ports(inputs),
+            &ports(outputs),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        client
+            .query_one(
+                "SELECT id FROM nodes WHERE name = $1 AND is_internal = TRUE",
+                &[&name],
+            )
+            .await
+            .unwrap()
+            .get("id")
+    }
+
+    fn graph_node(
+        id: &str,
+        node_id: Uuid,
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+    ) -> Value {
+        json!({
+            "id": id,
+            "type": "custom",
+            "position": { "x": 0.0, "y": 0.0 },
+            "data": {
+                "id": node_id.to_string(),
+                "name": name,
+                "inputs": inputs,
+                "outputs": outputs,
+            },
+            "measured": { "width": 0.0, "height": 0.0 },
+            "selected": false,
+            "dragging": false,
+        })
+    }
+
+    fn edge(source: &str, source_handle: &str, target: &str, target_handle: &str) -> Value {
+        json!({
+            "id": format!("{}-{}-{}-{}", source, source_handle, target, target_handle),
+            "source": source,
+            "sourceHandle": source_handle,
+            "target": target,
+            "targetHandle": target_handle,
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn dry_runs_leave_the_kv_store_of_the_pipeline_alone() {
+        let client = connect().await;
+        let begin = internal_node(&client, "BeginRequest", &[], &["data", "context"]).await;
+        let end = internal_node(&client, "EndRequest", &["data", "context"], &[]).await;
+        let writer = create_node(
+            &client,
+            "Counter",
+            "async function handle() {\n  await kv.set(\"visits\", 1);\n  return { value: await kv.get(\"visits\") };\n}",
+            &[],
+            &["value".to_string()],
+            None,
+            None,
+            None,
+            "javascript",
+        )
+        .await
+        .unwrap();
+        let pipeline = Pipeline {
+            id: Uuid::new_v4(),
+            name: "Counter".to_string(),
+            content: json!({
+                "nodes": [
+                    graph_node("begin", begin, "BeginRequest", &[], &["data", "context"]),
+                    graph_node("counter", writer.id, "Counter", &[], &["value"]),
+                    graph_node("end", end, "EndRequest", &["data", "context"], &[]),
+                ],
+                "edges": [
+                    edge("begin", "from-node", "counter", "to-node"),
+                    edge("counter", "from-node", "end", "to-node"),
+                    edge("counter", "output-value", "end", "input-data"),
+                ],
+            }),
+            error_handler_id: None,
+            allowed_hosts: vec![],
+            network_policy: None,
+        };
+
+        let run = dry_run(&client, &pipeline, json!({}), &json!({}), HashMap::new()).await;
+        delete_node(&client, writer.id).await.unwrap();
+
+        // The script saw its own write, which went to a scratch namespace
+        assert_eq!(run.unwrap().result, Some(json!(1)));
+        let stored = kv_get(&client, &pipeline.id.to_string(), "visits")
+            .await
+            .unwrap();
+        assert_eq!(stored, None);
+    }
+
+    #[tokio::test]
+    async fn handled_errors_are_answered_with_the_result_of_the_handler() {
+        let response = handler_response(Ok(json!({ "message": "Try again later" })));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({ "message": "Try again later" })
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_handlers_leave_the_request_failed() {
+        let response = handler_response(Err(AnyError::msg("Handler failed")));
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "Handler failed");
+    }
 }
@@ -1,5 +1,9 @@
-use actix_web::{delete, get, put, web, HttpResponse, Responder};
-use serde::Deserialize;
+use crate::db::environments::get_environment;
+use crate::db::models::{History, Pipeline};
+use crate::routes::exec::{create_run_history, execute_pipeline};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tokio_postgres::Client;
 use uuid::Uuid;
@@ -10,6 +14,41 @@ pub struct UpdateHistoryStatusRequest {
     status: String,
 }
 
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplayRevision {
+    /// The pipeline as it was when the original run happened. Only the pipeline is recorded, its
+    /// nodes run as they are saved now.
+    #[default]
+    Original,
+    /// The pipeline as it is saved now.
+    Current,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRequest {
+    #[serde(default)]
+    revision: ReplayRevision,
+}
+
+/// A change between the results of a run and its replay, at a JSON pointer into the results.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultChange {
+    path: String,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replay {
+    history: History,
+    original: History,
+    diff: Vec<ResultChange>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListHistoriesQuery {
@@ -68,6 +107,97 @@ pub async fn update_history_status(
     }
 }
 
+// Runs the request of a past run again, recording a new history that links back to it
+#[post("/{id}/replay")]
+pub async fn replay_history(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: Option<web::Json<ReplayRequest>>,
+) -> impl Responder {
+    let original = match crate::db::history::get_history(&client, id.into_inner()).await {
+        Ok(Some(original)) => original,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let (Some(input), Some(revision)) = (&original.input, &original.revision) else {
+        return HttpResponse::BadRequest().body("The run did not record its request and cannot be replayed");
+    };
+
+    let pipeline: Pipeline = match req.map(|req| req.into_inner().revision).unwrap_or_default() {
+        ReplayRevision::Original => match serde_json::from_value(revision.clone()) {
+            Ok(pipeline) => pipeline,
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Invalid recorded revision: {}", e)),
+        },
+        ReplayRevision::Current => match crate::db::get_pipeline(&client, original.pipeline_id).await {
+            Ok(Some(pipeline)) => pipeline,
+            Ok(None) => return HttpResponse::NotFound().body("The pipeline no longer exists"),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    };
+    // The variables of the environment are read as they are now, history does not record them
+    let environment_id = original
+        .context
+        .as_ref()
+        .and_then(|context| context.get("environmentId"))
+        .and_then(|id| serde_json::from_value::<Uuid>(id.clone()).ok());
+    let env = match environment_id {
+        Some(environment_id) => match get_environment(&client, environment_id).await {
+            Ok(Some(environment)) => environment.variables,
+            Ok(None) => return HttpResponse::BadRequest().body(format!("Environment {} of the run no longer exists", environment_id)),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+        None => json!({}),
+    };
+
+    let history = match create_run_history(&client, &pipeline, input, environment_id, Some(original.id)).await {
+        Ok(history) => history,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    // A failed replay is still reported, its history holds the error
+    let _ = execute_pipeline(&client, &pipeline, input.clone(), &env, history.id).await;
+
+    let history = match crate::db::history::get_history(&client, history.id).await {
+        Ok(Some(history)) => history,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let mut diff = vec![];
+    diff_values("", original.result.as_ref(), history.result.as_ref(), &mut diff);
+
+    HttpResponse::Ok().json(Replay {
+        history,
+        original,
+        diff,
+    })
+}
+
+// Collects the paths where two results differ, descending into objects and arrays
+fn diff_values(path: &str, before: Option<&Value>, after: Option<&Value>, changes: &mut Vec<ResultChange>) {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                diff_values(&path, before.get(key), after.get(key), changes);
+            }
+        }
+        (Some(Value::Array(before)), Some(Value::Array(after))) => {
+            for index in 0..before.len().max(after.len()) {
+                let path = format!("{}/{}", path, index);
+                diff_values(&path, before.get(index), after.get(index), changes);
+            }
+        }
+        (before, after) if before != after => changes.push(ResultChange {
+            path: path.to_string(),
+            before: before.cloned(),
+            after: after.cloned(),
+        }),
+        _ => {}
+    }
+}
+
 #[delete("/{id}")]
 pub async fn delete_history(
     client: web::Data<Arc<Client>>,
@@ -107,6 +237,7 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(list_histories_by_pipeline)
             .service(update_history_status)
             .service(delete_history)
+            .service(replay_history)
             .service(count_histories)
             .service(count_histories_by_pipeline),
     );
@@ -0,0 +1,69 @@
+use crate::db::kv;
+use actix_web::{delete, get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio_postgres::Client;
+
+#[derive(Deserialize)]
+pub struct ListEntriesQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[get("")]
+pub async fn list_namespaces(client: web::Data<Arc<Client>>) -> impl Responder {
+    match kv::list_namespaces(&client).await {
+        Ok(namespaces) => HttpResponse::Ok().json(namespaces),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{namespace}")]
+pub async fn list_entries(
+    client: web::Data<Arc<Client>>,
+    namespace: web::Path<String>,
+    query: web::Query<ListEntriesQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(10);
+    let offset = query.offset.unwrap_or(0);
+
+    match kv::list_entries(&client, &namespace, limit, offset).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{namespace}")]
+pub async fn clear_namespace(
+    client: web::Data<Arc<Client>>,
+    namespace: web::Path<String>,
+) -> impl Responder {
+    match kv::clear_namespace(&client, &namespace).await {
+        Ok(removed) => HttpResponse::Ok().json(json!({ "removed": removed })),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{namespace}/{key}")]
+pub async fn delete_entry(
+    client: web::Data<Arc<Client>>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (namespace, key) = path.into_inner();
+    match kv::kv_delete(&client, &namespace, &key).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/kv")
+            .service(list_namespaces)
+            .service(list_entries)
+            .service(clear_namespace)
+            .service(delete_entry),
+    );
+}
@@ -0,0 +1,101 @@
+use crate::db::libraries;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct LibraryRequest {
+    name: String,
+    source: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListLibrariesQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+// Library names are imported as bare specifiers, so they are kept to URL-safe characters
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[post("")]
+pub async fn create_library(
+    client: web::Data<Arc<Client>>,
+    req: web::Json<LibraryRequest>,
+) -> impl Responder {
+    if !valid_name(&req.name) {
+        return HttpResponse::BadRequest().body("Library names may only contain letters, digits, '-', '_' and '.'");
+    }
+
+    match libraries::create_library(&client, &req.name, &req.source).await {
+        Ok(library) => HttpResponse::Created().json(library),
+        Err(e) => HttpResponse::BadRequest().body(format!("Failed to create library: {}", e)),
+    }
+}
+
+#[get("/{id}")]
+pub async fn get_library(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match libraries::get_library(&client, id.into_inner()).await {
+        Ok(Some(library)) => HttpResponse::Ok().json(library),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[put("/{id}")]
+pub async fn update_library(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<LibraryRequest>,
+) -> impl Responder {
+    if !valid_name(&req.name) {
+        return HttpResponse::BadRequest().body("Library names may only contain letters, digits, '-', '_' and '.'");
+    }
+
+    match libraries::update_library(&client, id.into_inner(), &req.name, &req.source).await {
+        Ok(Some(library)) => HttpResponse::Ok().json(library),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::BadRequest().body(format!("Failed to update library: {}", e)),
+    }
+}
+
+#[delete("/{id}")]
+pub async fn delete_library(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match libraries::delete_library(&client, id.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+pub async fn list_libraries(
+    client: web::Data<Arc<Client>>,
+    query: web::Query<ListLibrariesQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(10);
+    let offset = query.offset.unwrap_or(0);
+
+    match libraries::list_libraries(&client, limit, offset).await {
+        Ok(libraries) => HttpResponse::Ok().json(libraries),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/libraries")
+            .service(create_library)
+            .service(list_libraries)
+            .service(get_library)
+            .service(update_library)
+            .service(delete_library),
+    );
+}
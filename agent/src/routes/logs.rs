@@ -1,12 +1,54 @@
+use crate::db::{LogCursor, LogFilter};
 use actix_web::{delete, get, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio_postgres::Client;
+use uuid::Uuid;
+
+const LOG_LEVELS: [&str; 3] = ["info", "warn", "error"];
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListLogsQuery {
     limit: Option<i64>,
     offset: Option<i64>,
+    /// Value of the `X-Next-Cursor` header of the previous page.
+    cursor: Option<String>,
+    /// Comma separated levels, e.g. `warn,error`.
+    level: Option<String>,
+    category: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    search: Option<String>,
+    pipeline_id: Option<Uuid>,
+    history_id: Option<Uuid>,
+}
+
+impl ListLogsQuery {
+    fn filter(&self) -> Result<LogFilter, String> {
+        let levels: Vec<String> = match &self.level {
+            Some(level) => level
+                .split(',')
+                .map(|level| level.trim().to_lowercase())
+                .filter(|level| !level.is_empty())
+                .collect(),
+            None => vec![],
+        };
+        if let Some(level) = levels.iter().find(|level| !LOG_LEVELS.contains(&level.as_str())) {
+            return Err(format!("Unknown log level: {}", level));
+        }
+
+        Ok(LogFilter {
+            levels,
+            category: self.category.clone(),
+            from: self.from,
+            to: self.to,
+            search: self.search.clone().filter(|search| !search.trim().is_empty()),
+            pipeline_id: self.pipeline_id,
+            history_id: self.history_id,
+        })
+    }
 }
 
 #[get("/{id}")]
@@ -25,9 +67,25 @@ pub async fn list_logs(
 ) -> impl Responder {
     let limit = query.limit.unwrap_or(30);
     let offset = query.offset.unwrap_or(0);
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let cursor = match query.cursor.as_deref().map(LogCursor::parse) {
+        Some(None) => return HttpResponse::BadRequest().body("Invalid cursor"),
+        Some(cursor) => cursor,
+        None => None,
+    };
 
-    match crate::db::list_logs(&client, limit, offset).await {
-        Ok(logs) => HttpResponse::Ok().json(logs),
+    match crate::db::list_logs(&client, &filter, cursor, limit, offset).await {
+        Ok(logs) => {
+            let mut response = HttpResponse::Ok();
+            // A full page may be followed by more logs
+            if let Some(last) = logs.last().filter(|_| logs.len() as i64 == limit) {
+                response.insert_header(("X-Next-Cursor", LogCursor::of(last).to_string()));
+            }
+            response.json(logs)
+        }
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
@@ -42,8 +100,16 @@ pub async fn delete_log(client: web::Data<Arc<Client>>, id: web::Path<i32>) -> i
 }
 
 #[get("/count")]
-pub async fn count_logs(client: web::Data<Arc<Client>>) -> impl Responder {
-    match crate::db::count_logs(&client).await {
+pub async fn count_logs(
+    client: web::Data<Arc<Client>>,
+    query: web::Query<ListLogsQuery>,
+) -> impl Responder {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    match crate::db::count_logs(&client, &filter).await {
         Ok(count) => HttpResponse::Ok().json(count),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
@@ -5,6 +5,12 @@ pub mod ping;
 pub mod pipelines;
 pub mod history;
 pub mod routes;
+pub mod secrets;
+pub mod environments;
+pub mod kv;
+pub mod libraries;
+pub mod tests;
+pub mod events;
 
 use actix_web::web;
 
@@ -15,7 +21,13 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .configure(nodes::configure)
             .configure(pipelines::configure)
             .configure(history::configure)
-            .configure(routes::configure),
+            .configure(routes::configure)
+            .configure(secrets::configure)
+            .configure(environments::configure)
+            .configure(kv::configure)
+            .configure(libraries::configure)
+            .configure(tests::configure)
+            .configure(events::configure),
     );
 
     cfg.service(ping::ping);
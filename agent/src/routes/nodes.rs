@@ -1,25 +1,89 @@
+use crate::db::models::Node;
+use crate::db::test_cases::list_node_test_cases;
+use crate::lint::{self, Diagnostic};
+use crate::routes::exec;
+use crate::testing;
+use crate::typescript;
 use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateNodeRequest {
     name: String,
     script: String,
     inputs: Vec<String>,
     outputs: Vec<String>,
+    config_schema: Option<serde_json::Value>,
+    input_schemas: Option<serde_json::Value>,
+    output_schemas: Option<serde_json::Value>,
+    #[serde(default = "default_language")]
+    language: String,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UpdateNodeRequest {
     name: String,
     script: String,
     inputs: Vec<String>,
     outputs: Vec<String>,
+    config_schema: Option<serde_json::Value>,
+    input_schemas: Option<serde_json::Value>,
+    output_schemas: Option<serde_json::Value>,
+    #[serde(default = "default_language")]
+    language: String,
 }
 
+// A saved node along with the problems found in its script, which do not prevent saving
+#[derive(Serialize)]
+pub struct SavedNode {
+    #[serde(flatten)]
+    node: Node,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn default_language() -> String {
+    typescript::JAVASCRIPT.to_string()
+}
+
+// Checks a script on the blocking thread pool, away from the workers serving requests. TypeScript
+// that does not transpile could never run, it is refused rather than saved.
+async fn check_script(
+    language: &str,
+    script: &str,
+    outputs: &[String],
+) -> Result<Vec<Diagnostic>, HttpResponse> {
+    let (language, script, outputs) = (language.to_string(), script.to_string(), outputs.to_vec());
+    let checked = web::block(move || -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        if language == typescript::TYPESCRIPT {
+            typescript::transpile(&script)?;
+        }
+        Ok(lint::check_node(&language, &script, &outputs))
+    })
+    .await;
+
+    match checked {
+        Ok(Ok(diagnostics)) => Ok(diagnostics),
+        Ok(Err(diagnostics)) => Err(HttpResponse::BadRequest()
+            .json(serde_json::json!({ "diagnostics": diagnostics }))),
+        Err(e) => Err(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RunNodeRequest {
+    #[serde(default)]
+    inputs: serde_json::Map<String, serde_json::Value>,
+    config: Option<serde_json::Value>,
+}
+
+// Blocks that only make sense as part of a pipeline graph
+const PIPELINE_BLOCKS: &[&str] = &["BeginRequest", "EndRequest", "ForEach", "Collect"];
+
 #[derive(Deserialize)]
 pub struct ListNodesQuery {
     limit: Option<i64>,
@@ -31,8 +95,28 @@ pub async fn create_node(
     client: web::Data<Arc<Client>>,
     req: web::Json<CreateNodeRequest>,
 ) -> impl Responder {
-    match crate::db::create_node(&client, &req.name, &req.script, &req.inputs, &req.outputs).await {
-        Ok(node) => HttpResponse::Created().json(node),
+    if !typescript::is_language(&req.language) {
+        return HttpResponse::BadRequest().body("Language must be either javascript or typescript");
+    }
+    let diagnostics = match check_script(&req.language, &req.script, &req.outputs).await {
+        Ok(diagnostics) => diagnostics,
+        Err(response) => return response,
+    };
+
+    match crate::db::create_node(
+        &client,
+        &req.name,
+        &req.script,
+        &req.inputs,
+        &req.outputs,
+        req.config_schema.as_ref(),
+        req.input_schemas.as_ref(),
+        req.output_schemas.as_ref(),
+        &req.language,
+    )
+        .await
+    {
+        Ok(node) => HttpResponse::Created().json(SavedNode { node, diagnostics }),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
@@ -52,6 +136,14 @@ pub async fn update_node(
     id: web::Path<Uuid>,
     req: web::Json<UpdateNodeRequest>,
 ) -> impl Responder {
+    if !typescript::is_language(&req.language) {
+        return HttpResponse::BadRequest().body("Language must be either javascript or typescript");
+    }
+    let diagnostics = match check_script(&req.language, &req.script, &req.outputs).await {
+        Ok(diagnostics) => diagnostics,
+        Err(response) => return response,
+    };
+
     match crate::db::update_node(
         &client,
         id.into_inner(),
@@ -59,15 +151,71 @@ pub async fn update_node(
         &req.script,
         &req.inputs,
         &req.outputs,
+        req.config_schema.as_ref(),
+        req.input_schemas.as_ref(),
+        req.output_schemas.as_ref(),
+        &req.language,
     )
         .await
     {
-        Ok(Some(node)) => HttpResponse::Ok().json(node),
+        Ok(Some(node)) => HttpResponse::Ok().json(SavedNode { node, diagnostics }),
         Ok(None) => HttpResponse::NotFound().finish(),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
+#[get("/{id}/typings")]
+pub async fn get_node_typings(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match crate::db::get_node(&client, id.into_inner()).await {
+        Ok(Some(node)) => HttpResponse::Ok()
+            .content_type("application/typescript")
+            .body(typescript::typings(&node)),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[post("/{id}/run")]
+pub async fn run_node(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<RunNodeRequest>,
+) -> impl Responder {
+    let node = match crate::db::get_node(&client, id.into_inner()).await {
+        Ok(Some(node)) => node,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if node.is_internal && PIPELINE_BLOCKS.contains(&node.name.as_str()) {
+        return HttpResponse::BadRequest().body(format!("{} can only run as part of a pipeline", node.name));
+    }
+
+    let req = req.into_inner();
+    match exec::run_node(&client, &node, req.inputs, req.config).await {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[post("/{id}/test")]
+pub async fn test_node(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    let id = id.into_inner();
+    let node = match crate::db::get_node(&client, id).await {
+        Ok(Some(node)) => node,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let test_cases = match list_node_test_cases(&client, id).await {
+        Ok(test_cases) => test_cases,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    match testing::run_node_tests(&client, &node, &test_cases).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
 #[delete("/{id}")]
 pub async fn delete_node(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
     match crate::db::delete_node(&client, id.into_inner()).await {
@@ -106,6 +254,9 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(create_node)
             .service(list_nodes)
             .service(get_node)
+            .service(get_node_typings)
+            .service(run_node)
+            .service(test_node)
             .service(update_node)
             .service(delete_node),
     );
@@ -1,5 +1,16 @@
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::db::environments::get_environment;
+use crate::db::{create_pipeline_log, LogLevel};
+use crate::debugger;
+use crate::db::models::Pipeline;
+use crate::db::test_cases::list_pipeline_test_cases;
+use crate::routes::exec;
+use crate::testing;
+use crate::validation::validate_pipeline;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use log::warn;
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio_postgres::Client;
 use uuid::Uuid;
@@ -9,6 +20,10 @@ use uuid::Uuid;
 pub struct CreatePipelineRequest {
     name: String,
     content: serde_json::Value,
+    error_handler_id: Option<Uuid>,
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+    network_policy: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -16,6 +31,50 @@ pub struct CreatePipelineRequest {
 pub struct UpdatePipelineRequest {
     name: String,
     content: serde_json::Value,
+    error_handler_id: Option<Uuid>,
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+    network_policy: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunRequest {
+    #[serde(default)]
+    input: Value,
+    #[serde(default, alias = "overrides")]
+    mocks: HashMap<String, Value>,
+    environment_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunGraphRequest {
+    content: Value,
+    #[serde(default)]
+    input: Value,
+    #[serde(default)]
+    mocks: HashMap<String, Value>,
+    environment_id: Option<Uuid>,
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+    network_policy: Option<Value>,
+    /// Pipeline the graph is a draft of. Its scripts only use the `kv` namespace of this pipeline
+    /// when `usePipelineKv` is set.
+    pipeline_id: Option<Uuid>,
+    /// Opts in to the live `kv` namespace of `pipelineId`, which the run can then change. Without
+    /// it the run gets a scratch namespace of its own, removed once the run is over.
+    #[serde(default)]
+    use_pipeline_kv: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePipelineQuery {
+    /// Saves the pipeline without running its test cases, every such save is logged as a
+    /// warning of the pipeline.
+    #[serde(default)]
+    skip_tests: bool,
 }
 
 #[derive(Deserialize)]
@@ -30,10 +89,19 @@ pub async fn create_pipeline(
     client: web::Data<Arc<Client>>,
     req: web::Json<CreatePipelineRequest>,
 ) -> impl Responder {
+    match validate_pipeline(&client, &req.content).await {
+        Ok(errors) if !errors.is_empty() => return HttpResponse::BadRequest().json(errors),
+        Ok(_) => {}
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
     match crate::db::create_pipeline(
         &client,
         &req.name,
         &req.content,
+        req.error_handler_id,
+        &req.allowed_hosts,
+        req.network_policy.as_ref(),
     )
         .await
     {
@@ -55,22 +123,191 @@ pub async fn get_pipeline(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -
 pub async fn update_pipeline(
     client: web::Data<Arc<Client>>,
     id: web::Path<Uuid>,
+    query: web::Query<UpdatePipelineQuery>,
     req: web::Json<UpdatePipelineRequest>,
 ) -> impl Responder {
+    let id = id.into_inner();
+    match validate_pipeline(&client, &req.content).await {
+        Ok(errors) if !errors.is_empty() => return HttpResponse::BadRequest().json(errors),
+        Ok(_) => {}
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    // The new revision is only saved when the test cases of the pipeline pass against it
+    if !query.skip_tests {
+        let test_cases = match list_pipeline_test_cases(&client, id).await {
+            Ok(test_cases) => test_cases,
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        };
+        if !test_cases.is_empty() {
+            let revision = Pipeline {
+                id,
+                name: req.name.clone(),
+                content: req.content.clone(),
+                error_handler_id: req.error_handler_id,
+                allowed_hosts: req.allowed_hosts.clone(),
+                network_policy: req.network_policy.clone(),
+            };
+            match testing::run_pipeline_tests(&client, &revision, &test_cases).await {
+                Ok(report) if report.failed > 0 => return HttpResponse::Conflict().json(report),
+                Ok(_) => {}
+                Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+            }
+        }
+    }
+
     match crate::db::update_pipeline(
         &client,
-        id.into_inner(),
+        id,
         &req.name,
         &req.content,
+        req.error_handler_id,
+        &req.allowed_hosts,
+        req.network_policy.as_ref(),
     )
         .await
     {
-        Ok(Some(pipeline)) => HttpResponse::Ok().json(pipeline),
+        Ok(Some(pipeline)) => {
+            if query.skip_tests {
+                let message = format!("Pipeline {} was saved without running its test cases", pipeline.name);
+                warn!("{}", message);
+                if let Err(e) = create_pipeline_log(&client, LogLevel::Warn, "Tests", &message, pipeline.id, None).await {
+                    warn!("Failed to log skipped tests: {}", e);
+                }
+            }
+            HttpResponse::Ok().json(pipeline)
+        }
         Ok(None) => HttpResponse::NotFound().finish(),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
+#[post("/{id}/test")]
+pub async fn test_pipeline(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    let id = id.into_inner();
+    let pipeline = match crate::db::get_pipeline(&client, id).await {
+        Ok(Some(pipeline)) => pipeline,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let test_cases = match list_pipeline_test_cases(&client, id).await {
+        Ok(test_cases) => test_cases,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    match testing::run_pipeline_tests(&client, &pipeline, &test_cases).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[post("/{id}/dry-run")]
+pub async fn dry_run_pipeline(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<DryRunRequest>,
+) -> impl Responder {
+    let pipeline = match crate::db::get_pipeline(&client, id.into_inner()).await {
+        Ok(Some(pipeline)) => pipeline,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let req = req.into_inner();
+    let env = match environment_variables(&client, req.environment_id).await {
+        Ok(env) => env,
+        Err(response) => return response,
+    };
+
+    match exec::dry_run(&client, &pipeline, req.input, &env, req.mocks).await {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// Runs a graph that is not saved, e.g. while it is being edited, without recording history
+#[post("/run")]
+pub async fn run_graph(
+    client: web::Data<Arc<Client>>,
+    req: web::Json<RunGraphRequest>,
+) -> impl Responder {
+    match validate_pipeline(&client, &req.content).await {
+        Ok(errors) if !errors.is_empty() => return HttpResponse::BadRequest().json(errors),
+        Ok(_) => {}
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let req = req.into_inner();
+    let env = match environment_variables(&client, req.environment_id).await {
+        Ok(env) => env,
+        Err(response) => return response,
+    };
+    let kv_namespace = match (req.use_pipeline_kv, req.pipeline_id) {
+        (true, Some(pipeline_id)) => pipeline_id.to_string(),
+        (true, None) => return HttpResponse::BadRequest().body("usePipelineKv requires a pipelineId"),
+        (false, _) => exec::scratch_namespace(),
+    };
+    // The graph is not a run of any pipeline, so it gets no pipeline id of its own
+    let pipeline = Pipeline {
+        id: Uuid::nil(),
+        name: "Unsaved pipeline".to_string(),
+        content: req.content,
+        error_handler_id: None,
+        allowed_hosts: req.allowed_hosts,
+        network_policy: req.network_policy,
+    };
+
+    let mut executor = match exec::pipeline_executor(&client, &pipeline, &env).await {
+        Ok(executor) => executor,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    executor.kv_namespace = kv_namespace.clone();
+    let run = exec::dry_run_executor(executor, req.input, req.mocks).await;
+
+    if !req.use_pipeline_kv {
+        exec::remove_scratch_namespace(&client, &kv_namespace).await;
+    }
+    HttpResponse::Ok().json(run)
+}
+
+// Variables of the environment a run was asked for, no variables when none was
+async fn environment_variables(
+    client: &Client,
+    environment_id: Option<Uuid>,
+) -> Result<Value, HttpResponse> {
+    let Some(environment_id) = environment_id else {
+        return Ok(json!({}));
+    };
+    match get_environment(client, environment_id).await {
+        Ok(Some(environment)) => Ok(environment.variables),
+        Ok(None) => Err(HttpResponse::BadRequest().body(format!("Environment {} does not exist", environment_id))),
+        Err(e) => Err(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+// Upgrades to a WebSocket serving a debug session of the pipeline
+#[get("/{id}/debug")]
+pub async fn debug_pipeline(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let pipeline = match crate::db::get_pipeline(&client, id.into_inner()).await {
+        Ok(Some(pipeline)) => pipeline,
+        Ok(None) => return Ok(HttpResponse::NotFound().finish()),
+        Err(e) => return Ok(HttpResponse::InternalServerError().body(e.to_string())),
+    };
+
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+    actix_web::rt::spawn(debugger::serve(
+        client.get_ref().clone(),
+        pipeline,
+        session,
+        stream,
+    ));
+    Ok(response)
+}
+
 #[delete("/{id}")]
 pub async fn delete_pipeline(
     client: web::Data<Arc<Client>>,
@@ -110,9 +347,13 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         web::scope("/pipelines")
             .service(count_pipelines)
             .service(create_pipeline)
+            .service(run_graph)
             .service(list_pipelines)
             .service(get_pipeline)
             .service(update_pipeline)
+            .service(test_pipeline)
+            .service(dry_run_pipeline)
+            .service(debug_pipeline)
             .service(delete_pipeline),
     );
 }
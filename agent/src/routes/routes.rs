@@ -14,12 +14,15 @@ pub struct RouteCreateRequest {
     pipeline_id: Uuid,
     path: String,
     method: String,
+    environment_id: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RouteUpdateRequest {
     path: String,
     method: String,
+    environment_id: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
@@ -38,6 +41,7 @@ pub async fn create_route(
         route.pipeline_id,
         &route.path,
         &route.method,
+        route.environment_id,
     ).await;
 
     match result {
@@ -123,7 +127,7 @@ pub async fn update_route(
     route: web::Json<RouteUpdateRequest>,
 ) -> impl Responder {
     let id = path.into_inner();
-    let result = routes::update_route(&client, id, &route.path, &route.method).await;
+    let result = routes::update_route(&client, id, &route.path, &route.method, route.environment_id).await;
 
     match result {
         Ok(route) => HttpResponse::Ok().json(route),
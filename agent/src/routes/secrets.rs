@@ -0,0 +1,91 @@
+use crate::db::secrets;
+use crate::secrets::encrypt;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateSecretRequest {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSecretRequest {
+    value: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListSecretsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[post("")]
+pub async fn create_secret(
+    client: web::Data<Arc<Client>>,
+    req: web::Json<CreateSecretRequest>,
+) -> impl Responder {
+    let value = match encrypt(&req.value) {
+        Ok(value) => value,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    match secrets::create_secret(&client, &req.name, &value).await {
+        Ok(secret) => HttpResponse::Created().json(secret),
+        Err(e) => HttpResponse::BadRequest().body(format!("Failed to create secret: {}", e)),
+    }
+}
+
+#[put("/{id}")]
+pub async fn update_secret(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<UpdateSecretRequest>,
+) -> impl Responder {
+    let value = match encrypt(&req.value) {
+        Ok(value) => value,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    match secrets::update_secret(&client, id.into_inner(), &value).await {
+        Ok(Some(secret)) => HttpResponse::Ok().json(secret),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{id}")]
+pub async fn delete_secret(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match secrets::delete_secret(&client, id.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+pub async fn list_secrets(
+    client: web::Data<Arc<Client>>,
+    query: web::Query<ListSecretsQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(10);
+    let offset = query.offset.unwrap_or(0);
+
+    match secrets::list_secrets(&client, limit, offset).await {
+        Ok(secrets) => HttpResponse::Ok().json(secrets),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/secrets")
+            .service(create_secret)
+            .service(list_secrets)
+            .service(update_secret)
+            .service(delete_secret),
+    );
+}
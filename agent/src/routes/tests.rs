@@ -0,0 +1,146 @@
+use crate::db::test_cases::{self, TestCaseFields};
+use crate::testing::parse_expectations;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCaseRequest {
+    name: String,
+    node_id: Option<Uuid>,
+    pipeline_id: Option<Uuid>,
+    #[serde(default = "empty_input")]
+    input: Value,
+    config: Option<Value>,
+    mocks: Option<Value>,
+    #[serde(default = "empty_expect")]
+    expect: Value,
+    environment_id: Option<Uuid>,
+}
+
+fn empty_input() -> Value {
+    Value::Object(Default::default())
+}
+
+fn empty_expect() -> Value {
+    Value::Array(vec![])
+}
+
+impl TestCaseRequest {
+    fn fields(&self) -> TestCaseFields<'_> {
+        TestCaseFields {
+            node_id: self.node_id,
+            pipeline_id: self.pipeline_id,
+            name: &self.name,
+            input: &self.input,
+            config: self.config.as_ref(),
+            mocks: self.mocks.as_ref(),
+            expect: &self.expect,
+            environment_id: self.environment_id,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.node_id.is_some() == self.pipeline_id.is_some() {
+            return Err("A test case belongs to either a node or a pipeline".to_string());
+        }
+        if self.node_id.is_some() && !self.input.is_object() {
+            return Err("Input of a node test case must be an object of its inputs".to_string());
+        }
+        if self.mocks.as_ref().is_some_and(|mocks| !mocks.is_object()) {
+            return Err("Mocks must be an object keyed by graph node id".to_string());
+        }
+        parse_expectations(&self.expect)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid expectations: {}", e))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTestCasesQuery {
+    node_id: Option<Uuid>,
+    pipeline_id: Option<Uuid>,
+}
+
+#[post("")]
+pub async fn create_test_case(
+    client: web::Data<Arc<Client>>,
+    req: web::Json<TestCaseRequest>,
+) -> impl Responder {
+    if let Err(message) = req.validate() {
+        return HttpResponse::BadRequest().body(message);
+    }
+
+    match test_cases::create_test_case(&client, &req.fields()).await {
+        Ok(test_case) => HttpResponse::Created().json(test_case),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{id}")]
+pub async fn get_test_case(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match test_cases::get_test_case(&client, id.into_inner()).await {
+        Ok(Some(test_case)) => HttpResponse::Ok().json(test_case),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[put("/{id}")]
+pub async fn update_test_case(
+    client: web::Data<Arc<Client>>,
+    id: web::Path<Uuid>,
+    req: web::Json<TestCaseRequest>,
+) -> impl Responder {
+    if let Err(message) = req.validate() {
+        return HttpResponse::BadRequest().body(message);
+    }
+
+    match test_cases::update_test_case(&client, id.into_inner(), &req.fields()).await {
+        Ok(Some(test_case)) => HttpResponse::Ok().json(test_case),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{id}")]
+pub async fn delete_test_case(client: web::Data<Arc<Client>>, id: web::Path<Uuid>) -> impl Responder {
+    match test_cases::delete_test_case(&client, id.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+pub async fn list_test_cases(
+    client: web::Data<Arc<Client>>,
+    query: web::Query<ListTestCasesQuery>,
+) -> impl Responder {
+    let result = match (query.node_id, query.pipeline_id) {
+        (Some(node_id), None) => test_cases::list_node_test_cases(&client, node_id).await,
+        (None, Some(pipeline_id)) => test_cases::list_pipeline_test_cases(&client, pipeline_id).await,
+        _ => return HttpResponse::BadRequest().body("Either nodeId or pipelineId is required"),
+    };
+
+    match result {
+        Ok(test_cases) => HttpResponse::Ok().json(test_cases),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/tests")
+            .service(create_test_case)
+            .service(list_test_cases)
+            .service(get_test_case)
+            .service(update_test_case)
+            .service(delete_test_case),
+    );
+}
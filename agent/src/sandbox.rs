@@ -0,0 +1,424 @@
+use crate::db::flow::NetworkPolicy;
+use crate::egress;
+use anyhow::Result;
+use deno_fetch::{FetchPermissions, FsError};
+use deno_permissions::{PermissionCheckError, PermissionDeniedError};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::borrow::Cow;
+use std::env;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+// Installed in the context of a script when a response size limit applies. Responses are read
+// up front so the limit holds whether or not the server sends a `Content-Length`.
+pub const LIMIT_FETCH_SCRIPT: &str = r#"
+(max) => {
+  const fetch = globalThis.fetch;
+  if (typeof fetch !== "function") return;
+  globalThis.fetch = async (...args) => {
+    const response = await fetch(...args);
+    const length = Number(response.headers.get("content-length"));
+    if (length > max) {
+      throw new Error(`Response of ${length} bytes exceeds the limit of ${max} bytes`);
+    }
+    if (!response.body) return response;
+
+    const reader = response.body.getReader();
+    const chunks = [];
+    let total = 0;
+    for (;;) {
+      const { done, value } = await reader.read();
+      if (done) break;
+      total += value.byteLength;
+      if (total > max) {
+        await reader.cancel();
+        throw new Error(`Response exceeds the limit of ${max} bytes`);
+      }
+      chunks.push(value);
+    }
+
+    const body = new Uint8Array(total);
+    let offset = 0;
+    for (const chunk of chunks) {
+      body.set(chunk, offset);
+      offset += chunk.byteLength;
+    }
+    return new Response(body, {
+      status: response.status,
+      statusText: response.statusText,
+      headers: response.headers,
+    });
+  };
+}
+"#;
+
+/// Reads the agent wide policy from `NET_ALLOWED_HOSTS`, `NET_ALLOWED_PORTS`,
+/// `NET_DENY_PRIVATE` and `NET_MAX_RESPONSE_BYTES`. Private addresses are denied unless
+/// `NET_DENY_PRIVATE` is `false`.
+pub fn global_policy() -> NetworkPolicy {
+    let list = |name: &str| {
+        env::var(name).ok().map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    NetworkPolicy {
+        allowed_hosts: list("NET_ALLOWED_HOSTS"),
+        allowed_ports: list("NET_ALLOWED_PORTS")
+            .map(|ports| ports.iter().filter_map(|port| port.parse().ok()).collect()),
+        deny_private: Some(env::var("NET_DENY_PRIVATE").map_or(true, |value| value != "false")),
+        max_response_bytes: env::var("NET_MAX_RESPONSE_BYTES")
+            .ok()
+            .and_then(|value| value.parse().ok()),
+    }
+}
+
+/// The network policies in force for the node being executed, a request has to be allowed by
+/// every one of them. Stored in the op state of the runtime, where `fetch` consults it.
+///
+/// Host names are not resolved when a URL is checked. The addresses a name resolves to are
+/// checked when connecting instead, by the egress proxy `fetch` goes through and by the resolver
+/// of the Http block, so a request only ever connects to an address that was checked.
+#[derive(Clone, Default)]
+pub struct NetworkSandbox {
+    policies: Vec<NetworkPolicy>,
+    node_policy: Option<NetworkPolicy>,
+    denials: Arc<Mutex<Vec<String>>>,
+    // Whether the active policies deny private addresses, read when connecting
+    deny_private: Arc<AtomicBool>,
+}
+
+impl NetworkSandbox {
+    pub fn new(policies: Vec<NetworkPolicy>) -> Self {
+        let sandbox = NetworkSandbox {
+            policies,
+            ..Default::default()
+        };
+        sandbox.update_deny_private();
+        sandbox
+    }
+
+    pub fn add_policy(&mut self, policy: NetworkPolicy) {
+        self.policies.push(policy);
+        self.update_deny_private();
+    }
+
+    pub fn set_node_policy(&mut self, policy: Option<NetworkPolicy>) {
+        self.node_policy = policy;
+        self.update_deny_private();
+    }
+
+    fn update_deny_private(&self) {
+        let deny_private = self
+            .active_policies()
+            .any(|policy| policy.deny_private == Some(true));
+        self.deny_private.store(deny_private, Ordering::Relaxed);
+    }
+
+    /// The proxy `fetch` of scripts connects through, it checks the addresses of every
+    /// connection against the policies of this sandbox.
+    pub fn egress_proxy(&self) -> Result<deno_tls::Proxy> {
+        egress::register(&self.deny_private, &self.denials)
+    }
+
+    /// A resolver for HTTP clients that leaves out the addresses the policies deny.
+    pub fn resolver(&self) -> Arc<PolicyResolver> {
+        Arc::new(PolicyResolver {
+            deny_private: self.deny_private.load(Ordering::Relaxed),
+            denials: self.denials.clone(),
+        })
+    }
+
+    fn active_policies(&self) -> impl Iterator<Item = &NetworkPolicy> {
+        self.policies.iter().chain(self.node_policy.iter())
+    }
+
+    /// The smallest response size limit of all policies.
+    pub fn max_response_bytes(&self) -> Option<u64> {
+        self.active_policies()
+            .filter_map(|policy| policy.max_response_bytes)
+            .min()
+    }
+
+    /// Checks a URL against every policy, a denial is recorded and returned as its reason.
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        let result = self.evaluate(url);
+        if let Err(reason) = &result {
+            record_denial(
+                &self.denials,
+                format!("Denied request to {}: {}", url, reason),
+            );
+        }
+        result
+    }
+
+    /// Removes and returns the denials recorded so far.
+    pub fn take_denials(&self) -> Vec<String> {
+        self.denials.lock().unwrap().drain(..).collect()
+    }
+
+    fn evaluate(&self, url: &Url) -> Result<(), String> {
+        let host = url.host_str().ok_or("the URL has no host")?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = url.port_or_known_default().ok_or("the URL has no port")?;
+
+        for policy in self.active_policies() {
+            if let Some(allowed_hosts) = &policy.allowed_hosts
+                && !host_allowed(host, allowed_hosts)
+            {
+                return Err(format!("host {} is not allowed", host));
+            }
+            if let Some(allowed_ports) = &policy.allowed_ports
+                && !allowed_ports.contains(&port)
+            {
+                return Err(format!("port {} is not allowed", port));
+            }
+        }
+
+        // Names are checked once resolved, when connecting
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            allowed_addresses(
+                host,
+                vec![SocketAddr::new(ip, port)],
+                self.deny_private.load(Ordering::Relaxed),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves host names for HTTP clients, leaving out the addresses denied by the policies of the
+/// sandbox it was made from.
+pub struct PolicyResolver {
+    deny_private: bool,
+    denials: Arc<Mutex<Vec<String>>>,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let deny_private = self.deny_private;
+        let denials = self.denials.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses = tokio::net::lookup_host((host, 0)).await?.collect();
+            match allowed_addresses(host, addresses, deny_private) {
+                Ok(addresses) => Ok(Box::new(addresses.into_iter()) as Addrs),
+                Err(reason) => {
+                    record_denial(&denials, format!("Denied request to {}: {}", host, reason));
+                    Err(reason.into())
+                }
+            }
+        })
+    }
+}
+
+pub fn record_denial(denials: &Mutex<Vec<String>>, denial: String) {
+    denials.lock().unwrap().push(denial);
+}
+
+/// The addresses a host resolved to that may be connected to, failing when there are none.
+pub fn allowed_addresses(
+    host: &str,
+    addresses: Vec<SocketAddr>,
+    deny_private: bool,
+) -> Result<Vec<SocketAddr>, String> {
+    if addresses.is_empty() {
+        return Err(format!("{} does not resolve to any address", host));
+    }
+    if !deny_private {
+        return Ok(addresses);
+    }
+
+    // A host resolving to a private address is denied even when it also resolves to public ones
+    match addresses.iter().find(|address| is_private(&address.ip())) {
+        Some(address) => Err(format!(
+            "{} resolves to the private address {}",
+            host,
+            address.ip()
+        )),
+        None => Ok(addresses),
+    }
+}
+
+impl FetchPermissions for NetworkSandbox {
+    fn check_net_url(&mut self, url: &Url, _api_name: &str) -> Result<(), PermissionCheckError> {
+        self.check_url(url).map_err(|reason| {
+            PermissionCheckError::PermissionDenied(PermissionDeniedError::Fatal {
+                access: format!("net access to {} ({})", url, reason),
+            })
+        })
+    }
+
+    // Scripts never read local files through `fetch`
+    fn check_read<'a>(
+        &mut self,
+        _resolved: bool,
+        _path: &'a Path,
+        _api_name: &str,
+    ) -> Result<Cow<'a, Path>, FsError> {
+        Err(FsError::NotCapable("read"))
+    }
+}
+
+/// Whether a host matches one of the allowlist entries, `*.example.com` also matches
+/// subdomains and `*` matches every host. Host names are compared ignoring case.
+pub fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.to_ascii_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        let allowed = allowed.to_ascii_lowercase();
+        match allowed.strip_prefix("*.") {
+            Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+            None => allowed == "*" || allowed == host,
+        }
+    })
+}
+
+fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                // "This network", 0.0.0.0/8
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // Reserved, 240.0.0.0/4
+                || a >= 240
+        }
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_private(&IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_multicast()
+                    // Unique local, fc00::/7
+                    || (first & 0xfe00) == 0xfc00
+                    // Link-local, fe80::/10
+                    || (first & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+// The IPv4 address an IPv6 address reaches, which is what has to be checked
+fn embedded_ipv4(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let [.., a, b, c, d] = ip.octets();
+    match ip.segments() {
+        // IPv4-mapped, ::ffff:0:0/96, and IPv4-compatible, ::/96. The loopback `::1` and the
+        // unspecified `::` read as 0.0.0.1 and 0.0.0.0.
+        [0, 0, 0, 0, 0, 0xffff | 0, _, _] => Some(Ipv4Addr::new(a, b, c, d)),
+        // NAT64, 64:ff9b::/96
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(Ipv4Addr::new(a, b, c, d)),
+        // 6to4, 2002::/16
+        [0x2002, high, low, ..] => Some(Ipv4Addr::from(((high as u32) << 16) | low as u32)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(ip: &str) -> SocketAddr {
+        SocketAddr::new(ip.parse().unwrap(), 443)
+    }
+
+    fn policy(deny_private: bool) -> NetworkPolicy {
+        NetworkPolicy {
+            allowed_hosts: Some(vec!["*.example.com".to_string()]),
+            allowed_ports: Some(vec![443]),
+            deny_private: Some(deny_private),
+            max_response_bytes: None,
+        }
+    }
+
+    #[test]
+    fn private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "0.1.2.3",
+            "224.0.0.1",
+            "255.255.255.255",
+            "ff02::1",
+            "::10.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::192.168.0.1",
+            "2002:a00:1::",
+            "2002:7f00:1::1",
+        ] {
+            assert!(is_private(&ip.parse().unwrap()), "{} is not private", ip);
+        }
+        for ip in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "64:ff9b::5db8:d822",
+            "2002:5db8:d822::1",
+        ] {
+            assert!(!is_private(&ip.parse().unwrap()), "{} is private", ip);
+        }
+    }
+
+    #[test]
+    fn hosts_match_ignoring_case() {
+        let allowed = vec!["*.Example.com".to_string(), "API.test".to_string()];
+
+        assert!(host_allowed("api.example.com", &allowed));
+        assert!(host_allowed("EXAMPLE.COM", &allowed));
+        assert!(host_allowed("api.TEST", &allowed));
+        assert!(!host_allowed("example.org", &allowed));
+        assert!(!host_allowed("badexample.com", &allowed));
+    }
+
+    #[test]
+    fn denies_hosts_resolving_to_a_private_address() {
+        let addresses = vec![address("93.184.216.34"), address("127.0.0.1")];
+        assert!(allowed_addresses("rebind.example.com", addresses.clone(), true).is_err());
+        assert_eq!(
+            allowed_addresses("rebind.example.com", addresses.clone(), false),
+            Ok(addresses)
+        );
+        assert!(allowed_addresses("empty.example.com", vec![], false).is_err());
+    }
+
+    #[test]
+    fn checks_urls_without_resolving() {
+        let sandbox = NetworkSandbox::new(vec![policy(true)]);
+        let check = |url: &str| sandbox.check_url(&Url::parse(url).unwrap());
+
+        assert!(check("https://api.example.com/").is_ok());
+        assert!(check("https://example.org/").is_err());
+        assert!(check("http://api.example.com/").is_err());
+        assert!(check("https://127.0.0.1/").is_err());
+        assert_eq!(sandbox.take_denials().len(), 3);
+    }
+
+    #[test]
+    fn node_policies_narrow_the_sandbox() {
+        let mut sandbox = NetworkSandbox::new(vec![NetworkPolicy::default()]);
+        assert!(!sandbox.deny_private.load(Ordering::Relaxed));
+        sandbox.set_node_policy(Some(policy(true)));
+        assert!(sandbox.deny_private.load(Ordering::Relaxed));
+        sandbox.set_node_policy(None);
+        assert!(!sandbox.deny_private.load(Ordering::Relaxed));
+    }
+}
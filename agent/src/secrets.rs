@@ -0,0 +1,314 @@
+use crate::db::flow::Graph;
+use crate::db::models::Node;
+use crate::db::secrets::get_secret_values;
+use crate::modules::imported_libraries;
+use crate::typescript::node_source;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::future::Future;
+use tokio_postgres::Client;
+
+const NONCE_LEN: usize = 12;
+const REDACTED: &str = "[REDACTED]";
+
+// Installed in the context of every script, `values` stays out of reach of the script itself
+pub const SECRETS_SCRIPT: &str = r#"
+(values) => {
+  const secrets = Object.freeze({ get: (name) => values[name] });
+  Object.defineProperty(globalThis, "secrets", { value: secrets });
+}
+"#;
+
+/// Names of the secrets a graph refers to, either as `{"$secret": "NAME"}` in the configuration
+/// of a graph node or as `secrets.get("NAME")` with a literal name in the script of a node or in
+/// a shared library it imports. `library_source` looks up the source of a library by its name.
+/// Only those secrets are loaded for a run, a name computed at run time reads as `undefined`.
+pub async fn referenced_names<F, Fut>(
+    graph: &Graph,
+    nodes: &[Node],
+    mut library_source: F,
+) -> Result<HashSet<String>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Option<String>>>,
+{
+    let mut names = HashSet::new();
+    for config in graph
+        .nodes
+        .iter()
+        .filter_map(|node| node.data.config.as_ref())
+    {
+        config_references(config, &mut names);
+    }
+
+    // Libraries import each other too, each one is read once
+    let mut imports = vec![];
+    for node in nodes {
+        script_references(&node.script, &mut names);
+        if let Ok(source) = node_source(node) {
+            imports.extend(imported_libraries(&source));
+        }
+    }
+    let mut libraries = HashSet::new();
+    while let Some(library) = imports.pop() {
+        if !libraries.insert(library.clone()) {
+            continue;
+        }
+        if let Some(source) = library_source(library).await? {
+            script_references(&source, &mut names);
+            imports.extend(imported_libraries(&source));
+        }
+    }
+    Ok(names)
+}
+
+fn config_references(value: &Value, names: &mut HashSet<String>) {
+    match value {
+        Value::Object(object) => {
+            if let (1, Some(Value::String(name))) = (object.len(), object.get("$secret")) {
+                names.insert(name.clone());
+            }
+            object
+                .values()
+                .for_each(|value| config_references(value, names));
+        }
+        Value::Array(items) => items.iter().for_each(|item| config_references(item, names)),
+        _ => {}
+    }
+}
+
+// A plain text search, a call in a comment only loads a secret the script does not read
+fn script_references(script: &str, names: &mut HashSet<String>) {
+    for (index, _) in script.match_indices("secrets.get(") {
+        let rest = script[index + "secrets.get(".len()..].trim_start();
+        let Some(quote) = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\'' | '`'))
+        else {
+            continue;
+        };
+        if let Some(end) = rest[1..].find(quote) {
+            names.insert(rest[1..1 + end].to_string());
+        }
+    }
+}
+
+// The master key is 32 bytes encoded as base64 in `SECRETS_KEY`
+fn cipher() -> Result<Aes256Gcm> {
+    let key = env::var("SECRETS_KEY").map_err(|_| anyhow!("SECRETS_KEY is not set"))?;
+    let key = STANDARD.decode(key.trim())?;
+    if key.len() != 32 {
+        return Err(anyhow!("SECRETS_KEY must be 32 bytes encoded as base64"));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+/// Encrypts a secret value, the random nonce is stored in front of the ciphertext.
+pub fn encrypt(value: &str) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()?
+        .encrypt(&nonce, value.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+pub fn decrypt(value: &[u8]) -> Result<String> {
+    if value.len() < NONCE_LEN {
+        return Err(anyhow!("Encrypted secret is truncated"));
+    }
+    let (nonce, ciphertext) = value.split_at(NONCE_LEN);
+    let plaintext = cipher()?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt secret, is SECRETS_KEY correct?"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Decrypted secrets of a pipeline run.
+#[derive(Default, Clone)]
+pub struct Secrets {
+    values: HashMap<String, String>,
+}
+
+impl Secrets {
+    /// Decrypts the secrets named in `names`. Without any name neither the database nor the key
+    /// is touched, so graphs without secrets run without `SECRETS_KEY`.
+    pub async fn load(client: &Client, names: &HashSet<String>) -> Result<Self> {
+        let mut values = HashMap::new();
+        if names.is_empty() {
+            return Ok(Secrets { values });
+        }
+        let names: Vec<String> = names.iter().cloned().collect();
+        for (name, value) in get_secret_values(client, &names).await? {
+            values.insert(name, decrypt(&value)?);
+        }
+        Ok(Secrets { values })
+    }
+
+    pub fn values(&self) -> &HashMap<String, String> {
+        &self.values
+    }
+
+    /// Replaces every `{"$secret": "NAME"}` object in a node configuration with the secret.
+    pub fn resolve(&self, value: Value) -> Result<Value> {
+        match value {
+            Value::Object(object) => {
+                if let (1, Some(Value::String(name))) = (object.len(), object.get("$secret")) {
+                    return self
+                        .values
+                        .get(name)
+                        .map(|secret| Value::String(secret.clone()))
+                        .ok_or_else(|| anyhow!("Secret {} does not exist", name));
+                }
+                let resolved = object
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, self.resolve(value)?)))
+                    .collect::<Result<_>>()?;
+                Ok(Value::Object(resolved))
+            }
+            Value::Array(items) => Ok(Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.resolve(item))
+                    .collect::<Result<_>>()?,
+            )),
+            other => Ok(other),
+        }
+    }
+
+    /// Masks every secret value occurring in a text.
+    pub fn redact(&self, text: &str) -> String {
+        let mut secrets: Vec<&String> = self
+            .values
+            .values()
+            .filter(|secret| !secret.is_empty())
+            .collect();
+        // Longer secrets first, so one containing another is masked as a whole
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+
+        let mut text = text.to_string();
+        for secret in secrets {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        text
+    }
+
+    /// Masks every secret value occurring in the strings of a JSON value.
+    pub fn redact_value(&self, value: Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.redact(&s)),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, self.redact_value(value)))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_literal_names_in_scripts() {
+        let mut names = HashSet::new();
+        script_references(
+            r#"
+            const token = secrets.get("API_TOKEN");
+            const user = secrets.get( 'DB_USER' );
+            const other = secrets.get(`WEBHOOK`);
+            const dynamic = secrets.get(name);
+            "#,
+            &mut names,
+        );
+
+        let expected = ["API_TOKEN", "DB_USER", "WEBHOOK"].map(String::from);
+        assert_eq!(names, HashSet::from(expected));
+    }
+
+    #[tokio::test]
+    async fn finds_references_in_imported_libraries() {
+        let graph: Graph = serde_json::from_value(serde_json::json!({
+            "nodes": [],
+            "edges": [],
+        }))
+        .unwrap();
+        let node = Node {
+            id: uuid::Uuid::new_v4(),
+            is_internal: false,
+            name: "Fetch".to_string(),
+            script: r#"
+                import { client } from "http";
+                export default async function handle() {
+                    return { value: await client(secrets.get("NODE_TOKEN")) };
+                }
+            "#
+            .to_string(),
+            inputs: vec![],
+            outputs: vec!["value".to_string()],
+            config_schema: None,
+            input_schemas: None,
+            output_schemas: None,
+            language: "javascript".to_string(),
+        };
+        let libraries = HashMap::from([
+            (
+                "http",
+                r#"
+                import { sign } from "lib:signing";
+                export const client = (token) => sign(token, secrets.get("HTTP_KEY"));
+                "#,
+            ),
+            (
+                "signing",
+                r#"
+                import "http";
+                export const sign = (token, key) => token + key + secrets.get('SIGNING_SALT');
+                "#,
+            ),
+        ]);
+
+        let mut loaded = vec![];
+        let names = referenced_names(&graph, &[node], |name| {
+            loaded.push(name.clone());
+            let source = libraries
+                .get(name.as_str())
+                .map(|source| source.to_string());
+            async move { Ok(source) }
+        })
+        .await
+        .unwrap();
+
+        let expected = ["NODE_TOKEN", "HTTP_KEY", "SIGNING_SALT"].map(String::from);
+        assert_eq!(names, HashSet::from(expected));
+        assert_eq!(loaded, vec!["http", "signing"]);
+    }
+
+    #[test]
+    fn finds_references_in_configurations() {
+        let mut names = HashSet::new();
+        config_references(
+            &serde_json::json!({
+                "token": { "$secret": "API_TOKEN" },
+                "headers": [{ "value": { "$secret": "HEADER" } }],
+                "plain": { "$secret": "NOT_A_REFERENCE", "other": true }
+            }),
+            &mut names,
+        );
+
+        let expected = ["API_TOKEN", "HEADER"].map(String::from);
+        assert_eq!(names, HashSet::from(expected));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::db::environments::get_environment;
+use crate::db::models::{Node, Pipeline, TestCase};
+use crate::routes::exec::{dry_run, error_json, run_node};
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_postgres::Client;
+
+/// An expectation on the result of a test case. `path` is a JSON pointer into the result, the
+/// whole result when empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Expectation {
+    #[serde(default)]
+    pub path: String,
+    pub op: ExpectOp,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpectOp {
+    /// The value equals `value`.
+    Equals,
+    /// The value does not equal `value`.
+    NotEquals,
+    /// Objects hold every key of `value` with a matching value, arrays hold an element matching
+    /// each element of `value` and strings contain `value`.
+    Contains,
+    /// The path resolves to a value.
+    Exists,
+    /// The path does not resolve to a value.
+    NotExists,
+    /// The value is of the JSON type named by `value`, e.g. `string` or `array`.
+    Type,
+}
+
+/// An expectation that did not hold, with the value found at its path.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpectationFailure {
+    pub path: String,
+    pub op: ExpectOp,
+    pub expected: Value,
+    pub actual: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResult {
+    pub test_case_id: uuid::Uuid,
+    pub name: String,
+    pub passed: bool,
+    pub duration_ms: i64,
+    /// Result of the pipeline or outputs of the node, secrets redacted.
+    pub actual: Option<Value>,
+    pub error: Option<Value>,
+    pub failures: Vec<ExpectationFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    fn new(results: Vec<TestResult>) -> Self {
+        let passed = results.iter().filter(|result| result.passed).count();
+        TestReport {
+            passed,
+            failed: results.len() - passed,
+            results,
+        }
+    }
+}
+
+/// Parses the stored expectations of a test case.
+pub fn parse_expectations(expect: &Value) -> Result<Vec<Expectation>> {
+    Ok(serde_json::from_value(expect.clone())?)
+}
+
+/// Runs the test cases of a node against its saved script.
+pub async fn run_node_tests(
+    client: &Arc<Client>,
+    node: &Node,
+    test_cases: &[TestCase],
+) -> Result<TestReport> {
+    let mut results = vec![];
+    for test_case in test_cases {
+        let inputs = test_case.input.as_object().cloned().unwrap_or_default();
+        let run = run_node(client, node, inputs, test_case.config.clone()).await?;
+        results.push(test_result(
+            test_case,
+            run.duration_ms,
+            run.outputs,
+            run.error,
+        )?);
+    }
+    Ok(TestReport::new(results))
+}
+
+/// Runs the test cases of a pipeline, none of the runs are recorded in history. `pipeline` may
+/// hold content that is not saved yet. Scripts use a scratch `kv` namespace for each test case.
+pub async fn run_pipeline_tests(
+    client: &Arc<Client>,
+    pipeline: &Pipeline,
+    test_cases: &[TestCase],
+) -> Result<TestReport> {
+    let mut results = vec![];
+    for test_case in test_cases {
+        let env = match test_case.environment_id {
+            Some(environment_id) => match get_environment(client, environment_id).await? {
+                Some(environment) => environment.variables,
+                None => {
+                    let error = json!({ "message": format!("Environment {} of the test case does not exist", environment_id) });
+                    results.push(test_result(test_case, 0, None, Some(error))?);
+                    continue;
+                }
+            },
+            None => json!({}),
+        };
+
+        // A dry run gets a scratch `kv` namespace, so neither saving a revision nor testing it
+        // changes the data of the pipeline or depends on it
+        let started_at = Utc::now();
+        let run = dry_run(
+            client,
+            pipeline,
+            test_case.input.clone(),
+            &env,
+            mocks(test_case),
+        )
+        .await;
+        let (actual, error) = match run {
+            Ok(run) => (run.result, run.error),
+            Err(e) => (None, Some(error_json(&e))),
+        };
+        let duration_ms = (Utc::now() - started_at).num_milliseconds();
+        results.push(test_result(test_case, duration_ms, actual, error)?);
+    }
+    Ok(TestReport::new(results))
+}
+
+fn mocks(test_case: &TestCase) -> HashMap<String, Value> {
+    match &test_case.mocks {
+        Some(Value::Object(mocks)) => mocks.clone().into_iter().collect(),
+        _ => HashMap::new(),
+    }
+}
+
+// A test case passes when its run succeeded and every expectation holds
+fn test_result(
+    test_case: &TestCase,
+    duration_ms: i64,
+    actual: Option<Value>,
+    error: Option<Value>,
+) -> Result<TestResult> {
+    let failures = match &actual {
+        Some(actual) => parse_expectations(&test_case.expect)?
+            .into_iter()
+            .filter_map(|expectation| check(&expectation, actual))
+            .collect(),
+        None => vec![],
+    };
+    Ok(TestResult {
+        test_case_id: test_case.id,
+        name: test_case.name.clone(),
+        passed: error.is_none() && failures.is_empty(),
+        duration_ms,
+        actual,
+        error,
+        failures,
+    })
+}
+
+// Checks an expectation against a result, returning the failure when it does not hold
+fn check(expectation: &Expectation, result: &Value) -> Option<ExpectationFailure> {
+    let actual = result.pointer(&expectation.path);
+    let holds = match (expectation.op, actual) {
+        (ExpectOp::Exists, actual) => actual.is_some(),
+        (ExpectOp::NotExists, actual) => actual.is_none(),
+        (ExpectOp::NotEquals, actual) => actual != Some(&expectation.value),
+        (_, None) => false,
+        (ExpectOp::Equals, Some(actual)) => *actual == expectation.value,
+        (ExpectOp::Contains, Some(actual)) => contains(actual, &expectation.value),
+        (ExpectOp::Type, Some(actual)) => expectation.value.as_str() == Some(type_name(actual)),
+    };
+    if holds {
+        return None;
+    }
+
+    Some(ExpectationFailure {
+        path: expectation.path.clone(),
+        op: expectation.op,
+        expected: expectation.value.clone(),
+        actual: actual.cloned(),
+    })
+}
+
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| contains(actual, value))
+        }),
+        (Value::Array(actual), Value::Array(expected)) => expected
+            .iter()
+            .all(|value| actual.iter().any(|actual| contains(actual, value))),
+        (Value::Array(actual), expected) => actual.iter().any(|actual| contains(actual, expected)),
+        (Value::String(actual), Value::String(expected)) => actual.contains(expected.as_str()),
+        (actual, expected) => actual == expected,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expect(path: &str, op: ExpectOp, value: Value) -> Expectation {
+        Expectation {
+            path: path.to_string(),
+            op,
+            value,
+        }
+    }
+
+    #[test]
+    fn objects_contain_a_subset_of_their_keys() {
+        let actual = json!({ "user": { "id": 1, "name": "Ada", "tags": ["a", "b"] }, "ok": true });
+
+        assert!(contains(&actual, &json!({ "ok": true })));
+        assert!(contains(&actual, &json!({ "user": { "name": "Ada" } })));
+        assert!(contains(&actual, &json!({ "user": { "tags": ["b"] } })));
+        assert!(!contains(&actual, &json!({ "user": { "name": "Bob" } })));
+        assert!(!contains(&actual, &json!({ "missing": null })));
+    }
+
+    #[test]
+    fn arrays_contain_matching_elements() {
+        let actual = json!([{ "id": 1, "done": true }, { "id": 2, "done": false }, 3]);
+
+        assert!(contains(&actual, &json!([{ "id": 2 }])));
+        assert!(contains(&actual, &json!([3, { "done": true }])));
+        assert!(contains(&actual, &json!({ "id": 1 })));
+        assert!(contains(&actual, &json!([])));
+        assert!(!contains(&actual, &json!([{ "id": 4 }])));
+        assert!(!contains(&actual, &json!([3, 4])));
+    }
+
+    #[test]
+    fn values_of_other_types_do_not_contain_each_other() {
+        assert!(contains(&json!("hello world"), &json!("world")));
+        assert!(!contains(&json!("hello world"), &json!("bye")));
+        assert!(!contains(&json!("1"), &json!(1)));
+        assert!(!contains(&json!({ "a": 1 }), &json!(["a"])));
+        assert!(!contains(&json!({ "a": "1" }), &json!({ "a": 1 })));
+        assert!(!contains(&json!(1), &json!("1")));
+        assert!(contains(&json!(1), &json!(1)));
+    }
+
+    #[test]
+    fn checks_expectations_at_their_path() {
+        let result = json!({ "user": { "name": "Ada", "roles": ["admin"] }, "count": 2 });
+
+        let holding = [
+            expect("/user/name", ExpectOp::Equals, json!("Ada")),
+            expect("/count", ExpectOp::NotEquals, json!(3)),
+            expect("/missing", ExpectOp::NotEquals, json!(3)),
+            expect(
+                "",
+                ExpectOp::Contains,
+                json!({ "user": { "roles": ["admin"] } }),
+            ),
+            expect("/user", ExpectOp::Exists, Value::Null),
+            expect("/user/email", ExpectOp::NotExists, Value::Null),
+            expect("/user/roles", ExpectOp::Type, json!("array")),
+            expect("/count", ExpectOp::Type, json!("number")),
+        ];
+        for expectation in holding {
+            assert!(check(&expectation, &result).is_none(), "{:?}", expectation);
+        }
+    }
+
+    #[test]
+    fn reports_failed_expectations_with_the_actual_value() {
+        let result = json!({ "count": 2, "name": "Ada" });
+
+        let failure = check(&expect("/count", ExpectOp::Equals, json!(3)), &result).unwrap();
+        assert_eq!(failure.path, "/count");
+        assert_eq!(failure.expected, json!(3));
+        assert_eq!(failure.actual, Some(json!(2)));
+
+        let failure = check(&expect("/count", ExpectOp::Type, json!("string")), &result).unwrap();
+        assert_eq!(failure.actual, Some(json!(2)));
+
+        let failure = check(&expect("/name", ExpectOp::NotExists, Value::Null), &result).unwrap();
+        assert_eq!(failure.actual, Some(json!("Ada")));
+
+        for op in [
+            ExpectOp::Equals,
+            ExpectOp::Contains,
+            ExpectOp::Type,
+            ExpectOp::Exists,
+        ] {
+            let failure = check(&expect("/missing", op, json!("x")), &result).unwrap();
+            assert_eq!(failure.actual, None);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use crate::db::models::Node;
+use crate::lint::Diagnostic;
+use anyhow::{anyhow, Result};
+use deno_ast::{
+    parse_program, EmitOptions, MediaType, ModuleSpecifier, ParseParams, SourceMapOption,
+    TranspileError, TranspileModuleOptions, TranspileOptions,
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+pub const JAVASCRIPT: &str = "javascript";
+pub const TYPESCRIPT: &str = "typescript";
+
+// Transpiled scripts kept in memory, keyed by the hash of their source
+const CACHE_CAPACITY: usize = 1024;
+
+static CACHE: LazyLock<Mutex<HashMap<[u8; 32], Arc<str>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn is_language(language: &str) -> bool {
+    language == JAVASCRIPT || language == TYPESCRIPT
+}
+
+/// Returns the JavaScript to run for the script of a node, transpiling TypeScript sources once
+/// and serving them from the cache afterwards.
+pub fn node_source(node: &Node) -> Result<Arc<str>> {
+    if node.language != TYPESCRIPT {
+        return Ok(node.script.as_str().into());
+    }
+
+    let key: [u8; 32] = Sha256::digest(node.script.as_bytes()).into();
+    if let Some(code) = CACHE.lock().unwrap().get(&key) {
+        return Ok(code.clone());
+    }
+
+    let code = transpile(&node.script).map_err(|diagnostics| {
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        anyhow!(
+            "TypeScript source of node {} has errors: {}",
+            node.name,
+            messages.join("; ")
+        )
+    })?;
+
+    let code: Arc<str> = code.into();
+    let mut cache = CACHE.lock().unwrap();
+    if cache.len() >= CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(key, code.clone());
+    Ok(code)
+}
+
+/// Transpiles TypeScript to JavaScript. Errors thrown by the script point into the transpiled
+/// code rather than into the source. Syntax errors are returned as diagnostics.
+pub fn transpile(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let parsed = parse_program(ParseParams {
+        specifier: ModuleSpecifier::parse("file:///node.ts").unwrap(),
+        text: source.into(),
+        media_type: MediaType::TypeScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|error| vec![Diagnostic::from(&error)])?;
+
+    let emit_options = EmitOptions {
+        source_map: SourceMapOption::None,
+        ..Default::default()
+    };
+    match parsed.transpile(
+        &TranspileOptions::default(),
+        &TranspileModuleOptions::default(),
+        &emit_options,
+    ) {
+        Ok(result) => Ok(result.into_source().text),
+        Err(TranspileError::ParseErrors(errors)) => {
+            Err(errors.0.iter().map(Diagnostic::from).collect())
+        }
+        Err(e) => Err(vec![Diagnostic {
+            line: 1,
+            column: 1,
+            message: e.to_string(),
+        }]),
+    }
+}
+
+/// Generates the typings of a node script as a global declaration file: the `Input`, `Config`
+/// and `Output` of its `handle` derived from the ports and schemas of the node, and the globals
+/// scripts can use.
+pub fn typings(node: &Node) -> String {
+    let mut typings = String::new();
+
+    typings.push_str("interface Input {\n");
+    for input in &node.inputs {
+        let schema = node.input_schemas.as_ref().and_then(|s| s.get(input));
+        typings.push_str(&format!(
+            "  {}?: {};\n",
+            property_name(input),
+            schema.map_or("unknown".to_string(), schema_type)
+        ));
+    }
+    typings.push_str("}\n\n");
+
+    typings.push_str(&format!(
+        "type Config = {};\n\n",
+        node.config_schema
+            .as_ref()
+            .map_or("Record<string, unknown>".to_string(), schema_type)
+    ));
+
+    typings.push_str("interface Output {\n");
+    for output in &node.outputs {
+        let schema = node.output_schemas.as_ref().and_then(|s| s.get(output));
+        typings.push_str(&format!(
+            "  {}?: {};\n",
+            property_name(output),
+            schema.map_or("unknown".to_string(), schema_type)
+        ));
+    }
+    typings.push_str("}\n\n");
+
+    typings.push_str(GLOBALS_TYPINGS);
+    typings
+}
+
+const GLOBALS_TYPINGS: &str = r#"type Handle = (input: Input, config: Config) => Output | Promise<Output>;
+
+declare const secrets: {
+  get(name: string): string | undefined;
+};
+
+declare const env: Readonly<Record<string, unknown>>;
+
+interface KvNamespace {
+  get(key: string): Promise<unknown>;
+  set(key: string, value: unknown, options?: { ttl?: number }): Promise<void>;
+  delete(key: string): Promise<boolean>;
+  cas(key: string, expected: unknown, value: unknown, options?: { ttl?: number }): Promise<boolean>;
+  namespace(name: string): KvNamespace;
+}
+
+declare const kv: KvNamespace;
+"#;
+
+fn property_name(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap()
+    }
+}
+
+// TypeScript type of a JSON Schema, or of a bare type name as accepted by the port schemas
+fn schema_type(schema: &Value) -> String {
+    match schema {
+        Value::String(name) => primitive_type(name).to_string(),
+        Value::Object(schema) => {
+            if let Some(Value::Array(values)) = schema.get("enum") {
+                let variants: Vec<String> = values.iter().map(Value::to_string).collect();
+                if !variants.is_empty() {
+                    return variants.join(" | ");
+                }
+            }
+            match schema.get("type") {
+                Some(Value::String(name)) if name == "array" => {
+                    let items = schema
+                        .get("items")
+                        .map_or("unknown".to_string(), schema_type);
+                    format!("Array<{}>", items)
+                }
+                Some(Value::String(name)) if name == "object" => object_type(schema),
+                Some(Value::String(name)) => primitive_type(name).to_string(),
+                Some(Value::Array(names)) => {
+                    let types: Vec<&str> = names
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(primitive_type)
+                        .collect();
+                    types.join(" | ")
+                }
+                _ if schema.contains_key("properties") => object_type(schema),
+                _ => "unknown".to_string(),
+            }
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+fn object_type(schema: &serde_json::Map<String, Value>) -> String {
+    let Some(Value::Object(properties)) = schema.get("properties") else {
+        return "Record<string, unknown>".to_string();
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let fields: Vec<String> = properties
+        .iter()
+        .map(|(name, schema)| {
+            format!(
+                "{}{}: {}",
+                property_name(name),
+                if required.contains(&name.as_str()) {
+                    ""
+                } else {
+                    "?"
+                },
+                schema_type(schema)
+            )
+        })
+        .collect();
+    format!("{{ {} }}", fields.join("; "))
+}
+
+fn primitive_type(name: &str) -> &'static str {
+    match name {
+        "string" => "string",
+        "number" | "integer" => "number",
+        "boolean" => "boolean",
+        "null" => "null",
+        "array" => "Array<unknown>",
+        "object" => "Record<string, unknown>",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpiles_typescript() {
+        let cases = [
+            (
+                "function handle(input: { name: string }, config: Config): Output {\n  return { greeting: `Hello ${input.name as string}` };\n}\n",
+                "function handle(input, config) {\n  return {\n    greeting: `Hello ${input.name}`\n  };\n}\n",
+            ),
+            (
+                "interface Point { x: number; y: number }\ntype Id = string | number;\nfunction handle(input: Input) {\n  const p: Point = { x: 1, y: 2 };\n  return { value: p.x };\n}\n",
+                "function handle(input) {\n  const p = {\n    x: 1,\n    y: 2\n  };\n  return {\n    value: p.x\n  };\n}\n",
+            ),
+            (
+                "import type { Thing } from \"lib\";\nimport { slugify } from \"text-utils\";\nexport default async function handle(input: Input): Promise<Output> {\n  const items = input.items as Array<Array<number>>;\n  return { slug: slugify(input.title!) };\n}\n",
+                "import { slugify } from \"text-utils\";\nexport default async function handle(input) {\n  const items = input.items;\n  return {\n    slug: slugify(input.title)\n  };\n}\n",
+            ),
+            (
+                "function handle(input: Input) {\n  const x = <number>input.value;\n  return { value: x satisfies number };\n}\n",
+                "function handle(input) {\n  const x = input.value;\n  return {\n    value: x\n  };\n}\n",
+            ),
+            (
+                "const handle = (input: Input): Output => ({ ok: true });\n",
+                "const handle = (input)=>({\n    ok: true\n  });\n",
+            ),
+            (
+                "enum Color { Red, Green }\nfunction handle(input: Input) {\n  return { color: Color.Green };\n}\n",
+                "var Color = /*#__PURE__*/ function(Color) {\n  Color[Color[\"Red\"] = 0] = \"Red\";\n  Color[Color[\"Green\"] = 1] = \"Green\";\n  return Color;\n}(Color || {});\nfunction handle(input) {\n  return {\n    color: Color.Green\n  };\n}\n",
+            ),
+            (
+                "namespace Util { export const two = 2; }\nfunction handle(input: Input) { return { value: Util.two }; }\n",
+                "(function(Util) {\n  Util.two = 2;\n})(Util || (Util = {}));\nfunction handle(input) {\n  return {\n    value: Util.two\n  };\n}\nvar Util;\n",
+            ),
+            (
+                "class Counter {\n  constructor(private readonly start: number) {}\n  next<T>(value: T): T { return value; }\n}\nfunction handle(input: Input) { return { value: new Counter(1).next<number>(2) }; }\n",
+                "class Counter {\n  start;\n  constructor(start){\n    this.start = start;\n  }\n  next(value) {\n    return value;\n  }\n}\nfunction handle(input) {\n  return {\n    value: new Counter(1).next(2)\n  };\n}\n",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(transpile(source).unwrap(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let diagnostics =
+            transpile("function handle(input: Input {\n  return {};\n}\n").unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 30));
+        assert_eq!(diagnostics[0].message, "Expected ',', got '{'");
+    }
+}
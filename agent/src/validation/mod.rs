@@ -0,0 +1,119 @@
+pub mod schema;
+
+use crate::db::flow::Graph;
+use crate::db::get_node;
+use crate::db::models::Node;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    pub node_id: Option<String>,
+    pub edge_id: Option<String>,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn node(node_id: &str, message: String) -> Self {
+        ValidationError {
+            node_id: Some(node_id.to_string()),
+            edge_id: None,
+            message,
+        }
+    }
+}
+
+/// Checks a pipeline graph before it is saved, returning every problem found.
+pub async fn validate_pipeline(client: &Client, content: &Value) -> Result<Vec<ValidationError>> {
+    let graph: Graph = match serde_json::from_value(content.clone()) {
+        Ok(graph) => graph,
+        Err(e) => {
+            return Ok(vec![ValidationError {
+                node_id: None,
+                edge_id: None,
+                message: format!("Invalid pipeline graph: {}", e),
+            }])
+        }
+    };
+
+    let mut errors = vec![];
+    let mut db_nodes: HashMap<&str, Node> = HashMap::new();
+    for graph_node in &graph.nodes {
+        let db_node = match graph_node.data.id.parse::<Uuid>() {
+            Ok(id) => get_node(client, id).await?,
+            Err(_) => None,
+        };
+        let Some(db_node) = db_node else {
+            errors.push(ValidationError::node(
+                &graph_node.id,
+                format!("Node {} does not exist", graph_node.data.id),
+            ));
+            continue;
+        };
+
+        if let Some(retry) = &graph_node.data.retry {
+            errors.extend(
+                retry
+                    .validate()
+                    .into_iter()
+                    .map(|message| ValidationError::node(&graph_node.id, message)),
+            );
+        }
+
+        if let Some(config_schema) = &db_node.config_schema {
+            let config =
+                schema::apply_defaults(Some(config_schema), graph_node.data.config.as_ref());
+            errors.extend(
+                schema::validate(config_schema, &config, "config")
+                    .into_iter()
+                    .map(|message| ValidationError::node(&graph_node.id, message)),
+            );
+        }
+
+        db_nodes.insert(&graph_node.id, db_node);
+    }
+
+    for edge in &graph.edges {
+        let (Some(output), Some(input)) = (
+            edge.source_handle.strip_prefix("output-"),
+            edge.target_handle.strip_prefix("input-"),
+        ) else {
+            continue;
+        };
+        let (Some(source), Some(target)) = (
+            db_nodes.get(edge.source.as_str()),
+            db_nodes.get(edge.target.as_str()),
+        ) else {
+            continue;
+        };
+        let (Some(output_schema), Some(input_schema)) = (
+            schema::port_schema(source.output_schemas.as_ref(), output),
+            schema::port_schema(target.input_schemas.as_ref(), input),
+        ) else {
+            continue;
+        };
+
+        if !schema::compatible(&output_schema, &input_schema) {
+            errors.push(ValidationError {
+                node_id: None,
+                edge_id: Some(edge.id.clone()),
+                message: format!(
+                    "Output {}.{} of type {} cannot connect to input {}.{} of type {}",
+                    source.name,
+                    output,
+                    output_schema["type"],
+                    target.name,
+                    input,
+                    input_schema["type"]
+                ),
+            });
+        }
+    }
+
+    Ok(errors)
+}
@@ -0,0 +1,194 @@
+use serde_json::{Map, Value};
+
+// A small subset of JSON Schema: `type`, `enum`, `properties`, `required`, `items` and `default`
+
+/// Validates a value against a schema, returning one message per violation. A secret reference
+/// `{"$secret": "NAME"}` matches any schema, it is only resolved when the node runs.
+pub fn validate(schema: &Value, value: &Value, path: &str) -> Vec<String> {
+    let mut errors = vec![];
+    let Some(schema) = schema.as_object() else {
+        return errors;
+    };
+    if is_secret_reference(value) {
+        return errors;
+    }
+
+    if let Some(types) = types(schema)
+        && !types.iter().any(|t| is_type(value, t))
+    {
+        errors.push(format!(
+            "{} should be {} but is {}",
+            path,
+            types.join(" or "),
+            type_name(value)
+        ));
+        return errors;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum")
+        && !options.contains(value)
+    {
+        errors.push(format!(
+            "{} should be one of {}",
+            path,
+            Value::Array(options.clone())
+        ));
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(|key| key.as_str()) {
+                if !object.contains_key(key) {
+                    errors.push(format!("{}.{} is required", path, key));
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                if let Some(item) = object.get(key) {
+                    errors.extend(validate(property, item, &format!("{}.{}", path, key)));
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            errors.extend(validate(item_schema, item, &format!("{}[{}]", path, index)));
+        }
+    }
+
+    errors
+}
+
+fn is_secret_reference(value: &Value) -> bool {
+    match value {
+        Value::Object(object) => {
+            object.len() == 1 && object.get("$secret").is_some_and(Value::is_string)
+        }
+        _ => false,
+    }
+}
+
+/// Fills in the `default` of every top level property missing from an object.
+pub fn apply_defaults(schema: Option<&Value>, value: Option<&Value>) -> Value {
+    let mut object = match value {
+        Some(Value::Object(object)) => object.clone(),
+        Some(other) => return other.clone(),
+        None => Map::new(),
+    };
+
+    if let Some(Value::Object(properties)) = schema.and_then(|schema| schema.get("properties")) {
+        for (key, property) in properties {
+            if let Some(default) = property.get("default") {
+                object.entry(key.clone()).or_insert_with(|| default.clone());
+            }
+        }
+    }
+
+    Value::Object(object)
+}
+
+/// Looks up the schema of a port, a bare type name like `"string"` is read as `{"type": "string"}`.
+pub fn port_schema(schemas: Option<&Value>, port: &str) -> Option<Value> {
+    match schemas?.get(port)? {
+        Value::String(t) => Some(serde_json::json!({ "type": t })),
+        Value::Array(ts) => Some(serde_json::json!({ "type": ts })),
+        schema @ Value::Object(_) => Some(schema.clone()),
+        _ => None,
+    }
+}
+
+/// Whether every value allowed by the `output` schema is also allowed by the `input` schema,
+/// judged on their `type` alone. A side without a type accepts anything.
+pub fn compatible(output: &Value, input: &Value) -> bool {
+    let (Some(output), Some(input)) = (
+        output.as_object().and_then(types),
+        input.as_object().and_then(types),
+    ) else {
+        return true;
+    };
+    if input.contains(&"any") {
+        return true;
+    }
+
+    output
+        .iter()
+        .all(|t| input.contains(t) || (*t == "integer" && input.contains(&"number")))
+}
+
+/// The names listed in the `type` of a schema, if any.
+pub fn types(schema: &Map<String, Value>) -> Option<Vec<&str>> {
+    let types: Vec<&str> = match schema.get("type")? {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => vec![],
+    };
+    if types.is_empty() {
+        None
+    } else {
+        Some(types)
+    }
+}
+
+fn is_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "any" => true,
+        _ => type_name(value) == expected,
+    }
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secret_references_match_any_type() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "token": { "type": "string" },
+                "port": { "type": "integer", "enum": [80, 443] },
+                "headers": { "type": "object", "required": ["Authorization"] }
+            }
+        });
+        let config = json!({
+            "token": { "$secret": "API_TOKEN" },
+            "port": { "$secret": "PORT" },
+            "headers": { "$secret": "HEADERS" }
+        });
+
+        assert!(validate(&schema, &config, "config").is_empty());
+    }
+
+    #[test]
+    fn objects_with_other_keys_are_not_secret_references() {
+        let schema = json!({ "type": "string" });
+
+        assert_eq!(
+            validate(
+                &schema,
+                &json!({ "$secret": "API_TOKEN", "other": 1 }),
+                "config"
+            ),
+            vec!["config should be string but is object"]
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "$secret": 1 }), "config"),
+            vec!["config should be string but is object"]
+        );
+    }
+}
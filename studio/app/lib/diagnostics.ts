@@ -0,0 +1,38 @@
+import type { Monaco, OnMount } from "@monaco-editor/react";
+
+export type ScriptEditor = Parameters<OnMount>[0];
+
+export interface Diagnostic {
+	line: number;
+	column: number;
+	message: string;
+}
+
+export function describeDiagnostics(diagnostics: Diagnostic[]) {
+	return diagnostics.map(d => `${d.line}:${d.column} ${d.message}`).join("\n");
+}
+
+// Diagnostics of a script the agent refused to save, null when it failed for another reason
+export async function rejectedDiagnostics(result: Response): Promise<Diagnostic[] | null> {
+	if (result.status !== 400) {
+		return null;
+	}
+	const body = await result.json().catch(() => null);
+	return Array.isArray(body?.diagnostics) ? body.diagnostics : null;
+}
+
+// Marks the diagnostics in the script editor, an empty list clears them
+export function markDiagnostics(monaco: Monaco, editor: ScriptEditor, diagnostics: Diagnostic[]) {
+	const model = editor.getModel();
+	if (!model) {
+		return;
+	}
+	monaco.editor.setModelMarkers(model, "agent", diagnostics.map(d => ({
+		severity: monaco.MarkerSeverity.Error,
+		message: d.message,
+		startLineNumber: d.line,
+		startColumn: d.column,
+		endLineNumber: d.line,
+		endColumn: model.getLineMaxColumn(Math.min(d.line, model.getLineCount())),
+	})));
+}
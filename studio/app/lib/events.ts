@@ -0,0 +1,28 @@
+import { serverAddress } from "./env";
+
+export type AgentEvent = {
+	pipelineId: string | null;
+	historyId: string | null;
+} & (
+	| { type: "executionStarted" }
+	| { type: "nodeStarted", nodeId: string, nodeName: string }
+	| { type: "nodeFinished", step: { nodeId: string, nodeName: string, status: string, durationMs: number, error: string | null } }
+	| { type: "executionFinished" }
+	| { type: "executionFailed", error: string }
+	| { type: "log", log: { id: number, level: "info" | "warn" | "error", category: string, message: string, createAt: string } }
+);
+
+// Subscribes to the live events of the agent, returns a function ending the subscription
+export function subscribeEvents(filter: { pipelineId?: string, historyId?: string }, onEvent: (event: AgentEvent) => void) {
+	const query = new URLSearchParams();
+	if (filter.pipelineId) {
+		query.set("pipelineId", filter.pipelineId);
+	}
+	if (filter.historyId) {
+		query.set("historyId", filter.historyId);
+	}
+
+	const source = new EventSource(serverAddress + "/api/events?" + query.toString());
+	source.onmessage = (message) => onEvent(JSON.parse(message.data));
+	return () => source.close();
+}
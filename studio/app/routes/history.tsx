@@ -7,6 +7,8 @@ import { TableHeader, TableRow, TableHead, TableBody, TableCell, Table } from "~
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from "~/components/ui/tooltip";
 import { serverAddress } from "~/lib/env";
 import MonacoEditorWrapper from "~/components/monacoEditorWrapper";
+import { toast } from "sonner";
+import { subscribeEvents } from "~/lib/events";
 
 type History = {
 	id: string;
@@ -16,18 +18,58 @@ type History = {
 	endAt?: string;
 	error?: string;
 	result?: any;
+	input?: any;
+	replayOf?: string;
+}
+
+type Replay = {
+	history: History;
+	diff: { path: string, before?: any, after?: any }[];
 }
 
 export default function HistoryPage() {
 	const [histories, setHistories] = useState<History[]>([]);
 
-	useEffect(() => {
+	function fetchHistories() {
 		fetch(serverAddress + "/api/history")
 			.then((response) => response.json())
 			.then((data) => setHistories(data))
 			.catch((error) => console.error("Error fetching histories:", error));
+	}
+
+	// Runs starting or ending elsewhere show up as they happen
+	useEffect(() => {
+		fetchHistories();
+		return subscribeEvents({}, (event) => {
+			if (event.type === "executionStarted" || event.type === "executionFinished" || event.type === "executionFailed") {
+				fetchHistories();
+			}
+		});
 	}, []);
 
+	async function replay(id: string, revision: "original" | "current") {
+		const result = await fetch(serverAddress + "/api/history/" + id + "/replay", {
+			method: "POST",
+			headers: {
+				"Content-Type": "application/json",
+			},
+			body: JSON.stringify({ revision }),
+		});
+
+		if (result.ok) {
+			const { history, diff }: Replay = await result.json();
+			setHistories([history, ...histories]);
+			const description = diff.map(change => `${change.path || "/"}: ${JSON.stringify(change.before)} → ${JSON.stringify(change.after)}`).join("\n");
+			if (diff.length > 0) {
+				toast.warning(`Replay ${history.status}, ${diff.length} change(s) in the result`, { description });
+			} else {
+				toast.success(`Replay ${history.status}, the result did not change`);
+			}
+		} else {
+			toast.error("Failed to replay the run.", { description: await result.text() });
+		}
+	}
+
 	return (
 		<FrameView title="History" subtitle="View history">
 			<Table>
@@ -82,6 +124,12 @@ export default function HistoryPage() {
 													<h3 className="font-semibold">Duration</h3>
 													<p className="font-mono ml-2 text-gray-600">{history.endAt ? (new Date(history.endAt).getTime() - new Date(history.startAt).getTime()) + "ms" : "N/A"}</p>
 												</div>
+												{history.replayOf && (
+													<div className="flex flex-col space-y-1">
+														<h3 className="font-semibold">Replay of</h3>
+														<p className="font-mono ml-2 text-gray-600">{history.replayOf}</p>
+													</div>
+												)}
 												<div className="flex flex-col space-y-1">
 													<h3 className="font-semibold">Error</h3>
 													<MonacoEditorWrapper 
@@ -117,6 +165,12 @@ export default function HistoryPage() {
 												<DrawerClose className="flex">
 													<Button variant="outline">Close</Button>
 												</DrawerClose>
+												{history.input !== undefined && history.input !== null && (
+													<>
+														<Button variant="secondary" onClick={() => replay(history.id, "current")}>Replay on current revision</Button>
+														<Button onClick={() => replay(history.id, "original")}>Replay</Button>
+													</>
+												)}
 											</DrawerFooter>
 										</DrawerContent>
 									</Drawer>
@@ -1,7 +1,11 @@
 import { useEffect, useState } from "react";
 import FrameView from "~/components/frame";
+import { Button } from "~/components/ui/button";
+import { Input } from "~/components/ui/input";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "~/components/ui/select";
 import { TableHeader, TableRow, TableHead, TableBody, TableCell, Table } from "~/components/ui/table";
 import { serverAddress } from "~/lib/env";
+import { subscribeEvents } from "~/lib/events";
 
 type Log = {
 	id: number;
@@ -9,20 +13,62 @@ type Log = {
 	category: string;
 	message: string;
 	createAt: string;
+	pipelineId?: string;
+	historyId?: string;
 };
 
 export default function LogsPage() {
 	const [logs, setLogs] = useState<Log[]>([]);
-	useEffect(() => {
-		fetch(serverAddress + "/api/logs")
-			.then((response) => response.json())
-			.then((data) => setLogs(data))
+	const [level, setLevel] = useState("all");
+	const [category, setCategory] = useState("");
+	const [search, setSearch] = useState("");
+	const [cursor, setCursor] = useState<string | null>(null);
+
+	const fetchLogs = (after: string | null) => {
+		const query = new URLSearchParams();
+		if (level !== "all") query.set("level", level);
+		if (category) query.set("category", category);
+		if (search) query.set("search", search);
+		if (after) query.set("cursor", after);
+
+		fetch(serverAddress + "/api/logs?" + query.toString())
+			.then((response) => {
+				setCursor(response.headers.get("X-Next-Cursor"));
+				return response.json();
+			})
+			.then((data) => setLogs((logs) => after ? [...logs, ...data] : data))
 			.catch((error) => console.error("Error fetching logs:", error));
-	}, []);
+	};
+
+	useEffect(() => {
+		fetchLogs(null);
+
+		return subscribeEvents({}, (event) => {
+			// New logs only show up in the unfiltered list
+			if (event.type === "log" && level === "all" && !category && !search) {
+				setLogs((logs) => [event.log, ...logs]);
+			}
+		});
+	}, [level, category, search]);
 
 	return (
 		<FrameView title="Logs" subtitle="View logs">
 			<div className="flex flex-col space-y-4">
+				<div className="flex flex-row space-x-2">
+					<Select value={level} onValueChange={setLevel}>
+						<SelectTrigger className="w-[180px]">
+							<SelectValue placeholder="Level" />
+						</SelectTrigger>
+						<SelectContent>
+							<SelectItem value="all">All levels</SelectItem>
+							<SelectItem value="info">Info</SelectItem>
+							<SelectItem value="warn">Warn</SelectItem>
+							<SelectItem value="error">Error</SelectItem>
+						</SelectContent>
+					</Select>
+					<Input type="text" className="w-[180px]" placeholder="Category" value={category} onChange={(e) => setCategory(e.target.value)} />
+					<Input type="text" className="grow" placeholder="Search messages" value={search} onChange={(e) => setSearch(e.target.value)} />
+				</div>
 				<Table>
 					<TableHeader>
 						<TableRow>
@@ -57,6 +103,9 @@ export default function LogsPage() {
 						})}
 					</TableBody>
 				</Table>
+				{cursor && (
+					<Button variant="outline" onClick={() => fetchLogs(cursor)}>Load more</Button>
+				)}
 			</div>
 		</FrameView>
 	)
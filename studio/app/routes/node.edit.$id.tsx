@@ -7,8 +7,18 @@ import FlowNode from "~/components/node";
 import ReactFlowWrapper from "~/components/reactflowWrapper";
 import { Button } from "~/components/ui/button";
 import { Input } from "~/components/ui/input";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "~/components/ui/select";
 import { serverAddress } from "~/lib/env";
 import type { NodeData } from "~/lib/nodeModel";
+import { type Diagnostic, type ScriptEditor, describeDiagnostics, markDiagnostics, rejectedDiagnostics } from "~/lib/diagnostics";
+import type { Monaco } from "@monaco-editor/react";
+
+interface NodeRun {
+	outputs: Record<string, unknown> | null;
+	console: { nodeId: string, level: string, message: string }[];
+	durationMs: number;
+	error: { message: string, stack?: string } | null;
+}
 
 const nodeTypes = { flowNode: FlowNode };
 
@@ -17,6 +27,15 @@ export default function NodeEditPage({ params }: { params: any }) {
 	const [script, setScript] = useState("function handle({ }) {\n\t// code here\n}\n");
 	const [inputs, setInputs] = useState<string[]>([]);
 	const [outputs, setOutputs] = useState<string[]>([]);
+	const [configSchema, setConfigSchema] = useState<object | null>(null);
+	const [inputSchemas, setInputSchemas] = useState<object | null>(null);
+	const [outputSchemas, setOutputSchemas] = useState<object | null>(null);
+	const [language, setLanguage] = useState("javascript");
+	const [typings, setTypings] = useState<string | null>(null);
+	const [monaco, setMonaco] = useState<Monaco | null>(null);
+	const [scriptEditor, setScriptEditor] = useState<ScriptEditor | null>(null);
+	const [runInputs, setRunInputs] = useState("{\n\t\"inputs\": {}\n}\n");
+	const [runResult, setRunResult] = useState<NodeRun | null>(null);
 
 	const [node, setNode] = useState<{ id: string, type: string, position: { x: number, y: number }, data: NodeData }>
 		({
@@ -38,13 +57,33 @@ export default function NodeEditPage({ params }: { params: any }) {
 				script,
 				inputs,
 				outputs,
+				configSchema,
+				inputSchemas,
+				outputSchemas,
+				language,
 			}),
 		});
 
 		if (result.ok) {
+			const { diagnostics }: { diagnostics: Diagnostic[] } = await result.json();
+			if (monaco && scriptEditor) {
+				markDiagnostics(monaco, scriptEditor, diagnostics);
+			}
+			if (diagnostics.length > 0) {
+				toast.warning("Node saved with problems", { description: describeDiagnostics(diagnostics) });
+				return;
+			}
 			navigate("/library");
 			toast.success("Node created successfully!");
 		} else {
+			const diagnostics = await rejectedDiagnostics(result);
+			if (diagnostics) {
+				if (monaco && scriptEditor) {
+					markDiagnostics(monaco, scriptEditor, diagnostics);
+				}
+				toast.error("Node not saved, the script has errors", { description: describeDiagnostics(diagnostics) });
+				return;
+			}
 			toast.error("Failed to create node. Please try again.");
 		}
 	}
@@ -65,6 +104,30 @@ export default function NodeEditPage({ params }: { params: any }) {
 		}
 	}
 
+	async function tryRun() {
+		let runBody;
+		try {
+			runBody = JSON.parse(runInputs);
+		} catch {
+			toast.error("Inputs must be valid JSON.");
+			return;
+		}
+
+		const result = await fetch(serverAddress + "/api/nodes/" + params.id + "/run", {
+			method: "POST",
+			headers: {
+				"Content-Type": "application/json",
+			},
+			body: JSON.stringify(runBody),
+		});
+
+		if (result.ok) {
+			setRunResult(await result.json());
+		} else {
+			toast.error("Failed to run node.", { description: await result.text() });
+		}
+	}
+
 	useEffect(() => {
 		fetch(serverAddress + "/api/nodes/" + params.id)
 			.then((response) => {
@@ -78,13 +141,32 @@ export default function NodeEditPage({ params }: { params: any }) {
 				setName(data.name);
 				setScript(data.script);
 				setInputs(data.inputs);
+				setConfigSchema(data.configSchema);
+				setInputSchemas(data.inputSchemas);
+				setOutputSchemas(data.outputSchemas);
 				setOutputs(data.outputs);
+				setLanguage(data.language);
 			})
 			.catch((error) => {
 				toast.error("Error fetching node data:", error);
 			});
+
+		fetch(serverAddress + "/api/nodes/" + params.id + "/typings")
+			.then((response) => response.ok ? response.text() : null)
+			.then(setTypings)
+			.catch(() => setTypings(null));
 	}, []);
 
+	// Types the input, config and globals of `handle` in the editor
+	useEffect(() => {
+		if (!monaco || !typings) {
+			return;
+		}
+		const libs = [monaco.languages.typescript.typescriptDefaults, monaco.languages.typescript.javascriptDefaults]
+			.map(defaults => defaults.addExtraLib(typings, "file:///node.d.ts"));
+		return () => libs.forEach(lib => lib.dispose());
+	}, [monaco, typings]);
+
 	return (
 		<FrameView title="Edit node" subtitle={params.id}>
 			<div className="flex flex-col space-y-4">
@@ -98,8 +180,22 @@ export default function NodeEditPage({ params }: { params: any }) {
 					}} />
 				</div>
 				<div className="space-y-1.5">
-					<h2 className="font-bold text-xl">Script</h2>
-					<MonacoEditorWrapper height={"56vh"} language="javascript" className="border border-gray-300" value={script} onChange={n => setScript(n ?? "")} />
+					<div className="flex flex-row items-center justify-between">
+						<h2 className="font-bold text-xl">Script</h2>
+						<Select value={language} onValueChange={setLanguage}>
+							<SelectTrigger className="w-[180px]">
+								<SelectValue placeholder="Language" />
+							</SelectTrigger>
+							<SelectContent>
+								<SelectItem value="javascript">JavaScript</SelectItem>
+								<SelectItem value="typescript">TypeScript</SelectItem>
+							</SelectContent>
+						</Select>
+					</div>
+					<MonacoEditorWrapper height={"56vh"} language={language} className="border border-gray-300" value={script} onChange={n => setScript(n ?? "")} onMount={(editor, monaco) => {
+						setScriptEditor(editor);
+						setMonaco(monaco);
+					}} />
 				</div>
 				<div className="space-y-1.5">
 					<h2 className="font-bold text-xl">Configuration</h2>
@@ -164,6 +260,31 @@ export default function NodeEditPage({ params }: { params: any }) {
 					</div>
 
 				</div>
+				<div className="space-y-1.5">
+					<div className="flex flex-row items-center justify-between">
+						<h2 className="font-bold text-xl">Try it</h2>
+						<Button variant={"secondary"} onClick={tryRun}>Run</Button>
+					</div>
+					<p className="text-sm text-gray-500">Runs the saved script with <code>{"{ inputs, config }"}</code> below.</p>
+					<div className="flex flex-row space-x-4">
+						<MonacoEditorWrapper height={"24vh"} language="json" className="border border-gray-300 w-1/2" value={runInputs} onChange={n => setRunInputs(n ?? "")} />
+						<div className="w-1/2 h-[24vh] overflow-auto border border-gray-300 p-2 font-mono text-sm space-y-2">
+							{runResult && (
+								<>
+									<div className="text-gray-500">Finished in {runResult.durationMs} ms</div>
+									{runResult.error
+										? <pre className="text-red-500 whitespace-pre-wrap">{runResult.error.stack ?? runResult.error.message}</pre>
+										: <pre className="whitespace-pre-wrap">{JSON.stringify(runResult.outputs, null, 2)}</pre>}
+									{runResult.console.map((entry, index) => (
+										<div key={index} className={entry.level === "error" ? "text-red-500" : entry.level === "warn" ? "text-yellow-600" : ""}>
+											[{entry.level}] {entry.message}
+										</div>
+									))}
+								</>
+							)}
+						</div>
+					</div>
+				</div>
 				<div className="flex flex-row space-x-4">
 					<Button onClick={submit}>Submit</Button>
 					<Button variant={"secondary"} onClick={() => navigate("/library")}>Cancel</Button>
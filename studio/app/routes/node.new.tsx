@@ -1,5 +1,6 @@
 import FrameView from "~/components/frame";
 import { Input } from "~/components/ui/input";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "~/components/ui/select";
 import { Button } from "~/components/ui/button";
 import FlowNode from "~/components/node";
 import ReactFlowWrapper from "~/components/reactflowWrapper";
@@ -9,6 +10,7 @@ import { serverAddress } from "~/lib/env";
 import { useNavigate } from "react-router";
 import { toast } from "sonner";
 import type { NodeData } from "~/lib/nodeModel";
+import { type Diagnostic, describeDiagnostics, rejectedDiagnostics } from "~/lib/diagnostics";
 
 const nodeTypes = { flowNode: FlowNode };
 
@@ -17,6 +19,7 @@ export default function NewNodePage() {
 	const [script, setScript] = useState("export function handle(data, context) {\n\t// code here\n}\n");
 	const [inputs, setInputs] = useState<string[]>([]);
 	const [outputs, setOutputs] = useState<string[]>([]);
+	const [language, setLanguage] = useState("javascript");
 
 	const [node, setNode] = useState<{ id: string, type: string, position: { x: number, y: number }, data: NodeData }>
 		({
@@ -38,13 +41,25 @@ export default function NewNodePage() {
 				script,
 				inputs,
 				outputs,
+				language,
 			}),
 		});
 
 		if (result.ok) {
+			const { id, diagnostics }: { id: string, diagnostics: Diagnostic[] } = await result.json();
+			if (diagnostics.length > 0) {
+				navigate("/node/edit/" + id);
+				toast.warning("Node created with problems", { description: describeDiagnostics(diagnostics) });
+				return;
+			}
 			navigate("/library");
 			toast.success("Node created successfully!");
 		} else {
+			const diagnostics = await rejectedDiagnostics(result);
+			if (diagnostics) {
+				toast.error("Node not created, the script has errors", { description: describeDiagnostics(diagnostics) });
+				return;
+			}
 			toast.error("Failed to create node. Please try again.");
 		}
 	}
@@ -129,8 +144,19 @@ export default function NewNodePage() {
 						</div>
 					</div>
 					<div className="space-y-1.5">
-						<h2 className="font-bold text-xl">Script</h2>
-						<MonacoEditorWrapper height={"56vh"} language="javascript" className="border border-gray-300" value={script} onChange={n => setScript(n ?? "")} />
+						<div className="flex flex-row items-center justify-between">
+							<h2 className="font-bold text-xl">Script</h2>
+							<Select value={language} onValueChange={setLanguage}>
+								<SelectTrigger className="w-[180px]">
+									<SelectValue placeholder="Language" />
+								</SelectTrigger>
+								<SelectContent>
+									<SelectItem value="javascript">JavaScript</SelectItem>
+									<SelectItem value="typescript">TypeScript</SelectItem>
+								</SelectContent>
+							</Select>
+						</div>
+						<MonacoEditorWrapper height={"56vh"} language={language} className="border border-gray-300" value={script} onChange={n => setScript(n ?? "")} />
 					</div>
 				</div>
 				<div className="flex flex-row space-x-4">
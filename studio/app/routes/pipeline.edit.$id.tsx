@@ -7,23 +7,38 @@ import { Input } from "~/components/ui/input";
 
 import '@xyflow/react/dist/style.css';
 import FlowNode from "~/components/node";
-import { fetchAllNodes, type NodeIndexData } from "~/lib/nodeModel";
+import { fetchAllNodes, type NodeData, type NodeIndexData } from "~/lib/nodeModel";
 import { toast } from "sonner";
 import { DrawerTrigger, DrawerContent, DrawerHeader, DrawerTitle, DrawerDescription, DrawerFooter, DrawerClose, Drawer } from "~/components/ui/drawer";
 import { v4 as uuidv4 } from 'uuid';
 import { useNavigate } from "react-router";
 import { serverAddress } from "~/lib/env";
+import MonacoEditorWrapper from "~/components/monacoEditorWrapper";
+import { subscribeEvents } from "~/lib/events";
 
 const nodeTypes = { flowNode: FlowNode };
 
+interface PipelineRun {
+	result: unknown;
+	error: { message: string, stack?: string } | null;
+	trace: { nodeId: string, nodeName: string, status: string, durationMs: number, error: string | null }[];
+	console: { nodeId: string, level: string, message: string }[];
+}
+
 export default function PipelineEditPage({ params }: { params: any }) {
 	const [name, setName] = useState("");
+	const [errorHandlerId, setErrorHandlerId] = useState<string | null>(null);
+	const [allowedHosts, setAllowedHosts] = useState<string[]>([]);
+	const [networkPolicy, setNetworkPolicy] = useState<object | null>(null);
 
 	const [nodes, setNodes, onNodesChange] = useNodesState([]);
 	const [edges, setEdges, onEdgesChange] = useEdgesState([]);
 
 	const [nodeAdditionDrawerOpen, setNodeAdditionDrawerOpen] = useState(false);
 
+	const [runInput, setRunInput] = useState("{}\n");
+	const [runResult, setRunResult] = useState<PipelineRun | null>(null);
+
 	const [availableNodes, setAvailableNodes] = useState<NodeIndexData[]>();
 	useEffect(() => {
 		fetchAllNodes()
@@ -71,6 +86,9 @@ export default function PipelineEditPage({ params }: { params: any }) {
 			},
 			body: JSON.stringify({
 				name,
+				errorHandlerId,
+				allowedHosts,
+				networkPolicy,
 				content: {
 					nodes,
 					edges
@@ -86,6 +104,63 @@ export default function PipelineEditPage({ params }: { params: any }) {
 		}
 	}
 
+	// Runs the graph as it is in the editor, without saving it
+	async function run() {
+		let input;
+		try {
+			input = JSON.parse(runInput);
+		} catch {
+			toast.error("Input must be valid JSON.");
+			return;
+		}
+		setNodeStatus(() => "idle");
+
+		const result = await fetch(serverAddress + "/api/pipelines/run", {
+			method: "POST",
+			headers: {
+				"Content-Type": "application/json",
+			},
+			body: JSON.stringify({
+				pipelineId: params.id,
+				allowedHosts,
+				networkPolicy,
+				input,
+				content: {
+					nodes,
+					edges
+				}
+			}),
+		});
+
+		if (result.ok) {
+			setRunResult(await result.json());
+		} else {
+			toast.error("Failed to run pipeline.", { description: await result.text() });
+		}
+	}
+
+	function setNodeStatus(status: (nodeId: string) => NodeData["status"] | undefined) {
+		// @ts-ignore
+		setNodes((nds) => nds.map((node) => {
+			const nodeStatus = status(node.id);
+			return nodeStatus ? { ...node, data: { ...node.data, status: nodeStatus } } : node;
+		}));
+	}
+
+	// Highlights the nodes of the pipeline while it runs
+	useEffect(() => {
+		return subscribeEvents({ pipelineId: params.id }, (event) => {
+			if (event.type === "executionStarted") {
+				setNodeStatus(() => "idle");
+			} else if (event.type === "nodeStarted") {
+				setNodeStatus((id) => id === event.nodeId ? "running" : undefined);
+			} else if (event.type === "nodeFinished") {
+				const status = event.step.status === "succeeded" ? "success" : event.step.status === "retrying" ? "running" : "error";
+				setNodeStatus((id) => id === event.step.nodeId ? status : undefined);
+			}
+		});
+	}, []);
+
 	useEffect(() => {
 		fetch(serverAddress + "/api/pipelines/" + params.id)
 			.then((response) => {
@@ -97,6 +172,9 @@ export default function PipelineEditPage({ params }: { params: any }) {
 			})
 			.then((data) => {
 				setName(data.name);
+				setErrorHandlerId(data.errorHandlerId);
+				setAllowedHosts(data.allowedHosts ?? []);
+				setNetworkPolicy(data.networkPolicy);
 				setNodes(data.content.nodes);
 				setEdges(data.content.edges);
 			})
@@ -148,6 +226,36 @@ export default function PipelineEditPage({ params }: { params: any }) {
 					</div>
 				</div>
 
+				<div className="space-y-1.5">
+					<div className="flex flex-row items-center justify-between">
+						<h2 className="font-bold text-xl">Run</h2>
+						<Button variant={"secondary"} onClick={run}>Run</Button>
+					</div>
+					<p className="text-sm text-gray-500">Runs the graph above with the input below without saving it.</p>
+					<div className="flex flex-row space-x-4">
+						<MonacoEditorWrapper height={"24vh"} language="json" className="border border-gray-300 w-1/2" value={runInput} onChange={n => setRunInput(n ?? "")} />
+						<div className="w-1/2 h-[24vh] overflow-auto border border-gray-300 p-2 font-mono text-sm space-y-2">
+							{runResult && (
+								<>
+									{runResult.error
+										? <pre className="text-red-500 whitespace-pre-wrap">{runResult.error.stack ?? runResult.error.message}</pre>
+										: <pre className="whitespace-pre-wrap">{JSON.stringify(runResult.result, null, 2)}</pre>}
+									{runResult.trace.map((step, index) => (
+										<div key={index} className={step.status === "failed" ? "text-red-500" : "text-gray-500"}>
+											{step.nodeName} {step.status} in {step.durationMs} ms{step.error && `: ${step.error}`}
+										</div>
+									))}
+									{runResult.console.map((entry, index) => (
+										<div key={index} className={entry.level === "error" ? "text-red-500" : entry.level === "warn" ? "text-yellow-600" : ""}>
+											[{entry.level}] {entry.message}
+										</div>
+									))}
+								</>
+							)}
+						</div>
+					</div>
+				</div>
+
 				<div className="flex flex-row space-x-4">
 					<Button onClick={submit}>Submit</Button>
 					<Button variant={"secondary"} onClick={() => navigate("/pipelines")}>Cancel</Button>